@@ -16,6 +16,13 @@ struct Uniforms {
     selection_end: vec2<f32>,
     time: f32,
     is_dragging: u32,
+    region_count: u32,
+    regions: array<vec4<f32>, 16>, // Additional selected regions, xy = start, zw = end
+    // Shapes as x = 0 rect, 1 ellipse, 2 lasso, y = first lasso point, z = lasso point count
+    drag_shape: vec4<u32>,
+    selection_shape: vec4<u32>,
+    region_shapes: array<vec4<u32>, 16>,
+    lasso_points: array<vec4<f32>, 128>, // Two points per element
 };
 
 @group(0) @binding(0) var t_diffuse: texture_2d<f32>;
@@ -41,18 +48,99 @@ fn rainbow(t: f32) -> vec4<f32> {
 }
 
 
-fn is_in_selection(coord: vec2<f32>) -> bool {
-    let min_pos = min(uniforms.selection_start, uniforms.selection_end);
-    let max_pos = max(uniforms.selection_start, uniforms.selection_end);
+fn lasso_point(i: u32) -> vec2<f32> {
+    let packed = uniforms.lasso_points[i / 2u];
+    if i % 2u == 0u {
+        return packed.xy;
+    }
+    return packed.zw;
+}
+
+fn is_in_lasso(coord: vec2<f32>, shape: vec4<u32>) -> bool {
+    // Even-odd rule
+    var inside = false;
+    var prev = lasso_point(shape.y + shape.z - 1u);
+    for (var i = 0u; i < shape.z; i++) {
+        let p = lasso_point(shape.y + i);
+        if (p.y > coord.y) != (prev.y > coord.y) &&
+           coord.x < (prev.x - p.x) * (coord.y - p.y) / (prev.y - p.y) + p.x {
+            inside = !inside;
+        }
+        prev = p;
+    }
+    return inside;
+}
+
+fn distance_to_lasso(coord: vec2<f32>, shape: vec4<u32>) -> f32 {
+    var dist = 1e9;
+    var prev = lasso_point(shape.y + shape.z - 1u);
+    for (var i = 0u; i < shape.z; i++) {
+        let p = lasso_point(shape.y + i);
+        let segment = p - prev;
+        let t = clamp(dot(coord - prev, segment) / max(dot(segment, segment), 1e-4), 0.0, 1.0);
+        dist = min(dist, length(coord - (prev + segment * t)));
+        prev = p;
+    }
+    return dist;
+}
+
+fn is_in_region(coord: vec2<f32>, region_start: vec2<f32>, region_end: vec2<f32>) -> bool {
+    let min_pos = min(region_start, region_end);
+    let max_pos = max(region_start, region_end);
     return coord.x >= min_pos.x && coord.x <= max_pos.x && 
            coord.y >= min_pos.y && coord.y <= max_pos.y;
 }
 
+fn is_in_shape(coord: vec2<f32>, region_start: vec2<f32>, region_end: vec2<f32>, shape: vec4<u32>) -> bool {
+    switch shape.x {
+        case 1u: {
+            let center = (region_start + region_end) * 0.5;
+            let radius = max(abs(region_end - region_start) * 0.5, vec2<f32>(1e-4));
+            let d = (coord - center) / radius;
+            return dot(d, d) <= 1.0;
+        }
+        case 2u: {
+            return is_in_lasso(coord, shape);
+        }
+        default: {
+            return is_in_region(coord, region_start, region_end);
+        }
+    }
+}
+
+fn is_on_shape_border(coord: vec2<f32>, region_start: vec2<f32>, region_end: vec2<f32>, shape: vec4<u32>, thickness: f32) -> bool {
+    var dist: f32;
+    switch shape.x {
+        case 1u: {
+            let center = (region_start + region_end) * 0.5;
+            let radius = max(abs(region_end - region_start) * 0.5, vec2<f32>(1e-4));
+            let d = (coord - center) / radius;
+            // Approximate distance to the outline in pixels
+            dist = abs(length(d) - 1.0) * min(radius.x, radius.y);
+        }
+        case 2u: {
+            dist = distance_to_lasso(coord, shape);
+        }
+        default: {
+            return is_on_border(coord, region_start, region_end, thickness);
+        }
+    }
+    if dist >= thickness {
+        return false;
+    }
+    // Create dashed effect
+    let dash_length = 10.0;
+    let animation_speed = 20.0;
+    let pos = coord.x + coord.y + uniforms.time * animation_speed;
+    return floor(pos / dash_length) % 2.0 < 1.0;
+}
+
+fn is_in_selection(coord: vec2<f32>) -> bool {
+    return is_in_shape(coord, uniforms.selection_start, uniforms.selection_end, uniforms.selection_shape);
+}
+
 fn is_in_drag(coord: vec2<f32>) -> bool {
-    let min_pos = min(uniforms.drag_start, uniforms.drag_end);
-    let max_pos = max(uniforms.drag_start, uniforms.drag_end);
-    return coord.x >= min_pos.x && coord.x <= max_pos.x && 
-           coord.y >= min_pos.y && coord.y <= max_pos.y;
+    return is_in_shape(coord, uniforms.drag_start, uniforms.drag_end, uniforms.drag_shape);
 }
 
 fn is_on_border(coord: vec2<f32>, region_start: vec2<f32>, region_end: vec2<f32>, thickness: f32) -> bool {
@@ -96,8 +184,9 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     var color = tex;
     let border_thickness = 2.0;
     
-    if (uniforms.is_dragging == 1u || uniforms.is_dragging == 3u) && is_in_drag(coord) {
-        if is_on_border(coord, uniforms.drag_start, uniforms.drag_end, border_thickness) {
+    // A lasso being drawn shows its outline, even where the path doesn't enclose anything yet
+    if (uniforms.is_dragging == 1u || uniforms.is_dragging == 3u) && (is_in_drag(coord) || uniforms.drag_shape.x == 2u) {
+        if is_on_shape_border(coord, uniforms.drag_start, uniforms.drag_end, uniforms.drag_shape, border_thickness) {
             color = vec4<f32>(0.0, 0.5, 1.0, 1.0);  // Blue border
         }
         //  else if get_stripe_pattern(coord) {
@@ -105,8 +194,20 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         // }
     }
     
+    for (var i = 0u; i < uniforms.region_count; i++) {
+        let region = uniforms.regions[i];
+        let shape = uniforms.region_shapes[i];
+        if is_in_shape(coord, region.xy, region.zw, shape) {
+            if is_on_shape_border(coord, region.xy, region.zw, shape, border_thickness) {
+                color = mix(color, vec4<f32>(0.0, 1.0, 0.0, 1.0), 0.5);  // Green border
+            } else if get_stripe_pattern(coord) {
+                color = mix(color, vec4<f32>(0.0, 0.5, 1.0, 0.3), 0.1);  // Semi-transparent blue stripes
+            }
+        }
+    }
+
     if (uniforms.is_dragging == 2u || uniforms.is_dragging == 3u) && is_in_selection(coord) {
-        if is_on_border(coord, uniforms.selection_start, uniforms.selection_end, border_thickness) {
+        if is_on_shape_border(coord, uniforms.selection_start, uniforms.selection_end, uniforms.selection_shape, border_thickness) {
             color = mix(color, vec4<f32>(0.0, 1.0, 0.0, 1.0), 0.5);  // Green border
         } else if get_stripe_pattern(coord) {
             color = mix(color, vec4<f32>(0.0, 0.5, 1.0, 0.3), 0.1);  // Semi-transparent blue stripes
@@ -95,6 +95,12 @@ where
         }
     }
 
+    pub fn set_texture(&mut self, img: DynamicImage, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let texture = texture::RenderTexture::from_image(device, queue, &img, None)
+            .expect("Could not load texture");
+        self.texture_bundle.set_texture(texture, device);
+    }
+
     pub fn update_buffer(&self, queue: &wgpu::Queue) {
         queue.write_buffer(
             &self.uniform_buffer,
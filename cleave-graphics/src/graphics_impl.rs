@@ -95,6 +95,16 @@ where
         })
     }
 
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.size = UVec2::new(width, height);
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+    }
+
     fn output(&self) -> Option<GraphicsOutput> {
         let Ok(output) = self.surface.get_current_texture() else {
             println!("No output available");
@@ -107,7 +117,7 @@ where
         Some(GraphicsOutput { output, view })
     }
 
-    pub fn render(&mut self) -> GraphicsResult<GraphicsPass<W>> {
+    pub fn render(&mut self) -> GraphicsResult<GraphicsPass<'_, '_, W>> {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
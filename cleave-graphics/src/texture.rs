@@ -33,26 +33,39 @@ impl TextureBundle {
                 },
             ],
         });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                },
-            ],
-            layout: &bind_group_layout,
-            label: None,
-        });
+        let bind_group = make_bind_group(&texture, device, &bind_group_layout);
         Self {
             // texture,
             bind_group,
             bind_group_layout,
         }
     }
+
+    /// Binds a different texture, keeping the layout that pipelines were created with.
+    pub fn set_texture(&mut self, texture: RenderTexture, device: &wgpu::Device) {
+        self.bind_group = make_bind_group(&texture, device, &self.bind_group_layout);
+    }
+}
+
+fn make_bind_group(
+    texture: &RenderTexture,
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+        layout,
+        label: None,
+    })
 }
 
 pub struct RenderTexture {
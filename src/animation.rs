@@ -0,0 +1,184 @@
+use std::{fs::File, io::BufWriter, path::Path, time::Duration};
+
+use anyhow::Context;
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        webp::WebPEncoder,
+    },
+    Delay, ExtendedColorType, Frame, RgbaImage,
+};
+use serde::Deserialize;
+
+/// File formats for a sequence of frames.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Small and plays everywhere, but limited to 256 colors per frame
+    #[default]
+    Gif,
+    /// Animated PNG, lossless
+    Apng,
+    /// Animated WebP, lossless
+    Webp,
+}
+
+impl Format {
+    /// Guesses the format from the extension of `path`, `.png` is taken as APNG.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Format::Gif),
+            "png" | "apng" => Some(Format::Apng),
+            "webp" => Some(Format::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Gif => "gif",
+            Format::Apng => "png",
+            Format::Webp => "webp",
+        }
+    }
+}
+
+/// Writes `frames` to `path` as an endlessly looping animation, showing each frame for the
+/// matching entry of `delays`.
+pub fn save(
+    frames: &[RgbaImage],
+    delays: &[Duration],
+    format: Format,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let first = frames.first().context("Nothing was recorded")?;
+    anyhow::ensure!(frames.len() == delays.len(), "Every frame needs a delay");
+    let file =
+        File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    let writer = BufWriter::new(file);
+    match format {
+        Format::Gif => {
+            let mut encoder = GifEncoder::new_with_speed(writer, 10);
+            encoder.set_repeat(Repeat::Infinite)?;
+            // Quantizes every frame to its own palette
+            encoder.encode_frames(frames.iter().zip(delays).map(|(frame, delay)| {
+                Frame::from_parts(frame.clone(), 0, 0, Delay::from_saturating_duration(*delay))
+            }))?;
+        }
+        Format::Apng => {
+            let mut encoder = png::Encoder::new(writer, first.width(), first.height());
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(frames.len() as u32, 0)?;
+            let mut writer = encoder.write_header()?;
+            for (frame, delay) in frames.iter().zip(delays) {
+                writer.set_frame_delay(millis(*delay).min(u16::MAX as u32) as u16, 1000)?;
+                writer.write_image_data(frame.as_raw())?;
+            }
+            writer.finish()?;
+        }
+        Format::Webp => {
+            let mut writer = writer;
+            std::io::Write::write_all(&mut writer, &encode_webp(frames, delays)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Encodes every frame as a lossless still, and wraps those in the chunks of an animated WebP.
+fn encode_webp(frames: &[RgbaImage], delays: &[Duration]) -> anyhow::Result<Vec<u8>> {
+    let (width, height) = frames[0].dimensions();
+    let mut chunks = Vec::new();
+    let mut header = vec![0x10 | 0x02, 0, 0, 0]; // Alpha and animation flags
+    header.extend(u24(width - 1));
+    header.extend(u24(height - 1));
+    push_chunk(&mut chunks, b"VP8X", &header);
+    push_chunk(&mut chunks, b"ANIM", &[0, 0, 0, 0, 0, 0]); // Transparent background, loop forever
+
+    for (frame, delay) in frames.iter().zip(delays) {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still).encode(
+            frame.as_raw(),
+            frame.width(),
+            frame.height(),
+            ExtendedColorType::Rgba8,
+        )?;
+        let bitstream = find_chunk(&still, b"VP8L").context("Could not encode WebP frame")?;
+        let mut anmf = Vec::new();
+        anmf.extend(u24(0)); // Offset
+        anmf.extend(u24(0));
+        anmf.extend(u24(frame.width() - 1));
+        anmf.extend(u24(frame.height() - 1));
+        anmf.extend(u24(millis(*delay).min(0xff_ffff))); // Duration in milliseconds
+        anmf.push(0x02); // Replace the previous frame instead of blending
+        push_chunk(&mut anmf, b"VP8L", bitstream);
+        push_chunk(&mut chunks, b"ANMF", &anmf);
+    }
+
+    let mut webp = Vec::with_capacity(chunks.len() + 12);
+    webp.extend(b"RIFF");
+    webp.extend((chunks.len() as u32 + 4).to_le_bytes());
+    webp.extend(b"WEBP");
+    webp.extend(chunks);
+    Ok(webp)
+}
+
+fn millis(delay: Duration) -> u32 {
+    delay.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+fn push_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    out.extend(fourcc);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0); // Chunks are padded to an even size
+    }
+}
+
+/// The payload of the first chunk of type `fourcc` in a WebP file.
+fn find_chunk<'a>(webp: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut offset = 12; // After the RIFF header
+    while offset + 8 <= webp.len() {
+        let size = u32::from_le_bytes(webp[offset + 4..offset + 8].try_into().ok()?) as usize;
+        let data = webp.get(offset + 8..offset + 8 + size)?;
+        if &webp[offset..offset + 4] == fourcc {
+            return Some(data);
+        }
+        offset += 8 + size + size % 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::gif::GifDecoder, AnimationDecoder, Rgba};
+
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_delay_of_every_frame() {
+        let path = std::env::temp_dir().join(format!("cleave-delays-{}.gif", std::process::id()));
+        let frames: Vec<_> = [0, 128, 255]
+            .map(|value| RgbaImage::from_pixel(4, 4, Rgba([value, 0, 0, 255])))
+            .into();
+        let delays = [100, 500, 60].map(Duration::from_millis);
+        save(&frames, &delays, Format::Gif, &path).unwrap();
+        let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let decoded: Vec<_> = decoder
+            .into_frames()
+            .map(|frame| Duration::from(frame.unwrap().delay()))
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded, delays);
+        assert!(save(&frames, &delays[..2], Format::Gif, &path).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::HistoryConfig, export::Rect};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Stored as JSON next to every image in the history.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sidecar {
+    pub timestamp: u64, // Milliseconds since the Unix epoch
+    pub monitor: String,
+    pub rect: Option<Rect>, // Bounds of the selection on the monitor
+}
+
+/// A capture in the history.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub image: PathBuf,
+    pub sidecar: Sidecar,
+}
+
+impl Entry {
+    pub fn delete(&self) -> anyhow::Result<()> {
+        std::fs::remove_file(&self.image)
+            .with_context(|| format!("Could not delete {}", self.image.display()))?;
+        let _ = std::fs::remove_file(self.image.with_extension("json"));
+        Ok(())
+    }
+}
+
+/// Adds a capture to the history, unless that is disabled in `config`.
+pub fn record(
+    config: &HistoryConfig,
+    image: &RgbaImage,
+    monitor: &str,
+    rect: Option<Rect>,
+) -> anyhow::Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    Archive::new(config)?.add(image, monitor, rect)
+}
+
+/// Recent captures kept in `<data dir>/cleave/history`, as `<timestamp>.png` and `<timestamp>.json`.
+pub struct Archive {
+    dir: PathBuf,
+    max_entries: usize,
+    max_age: Option<Duration>,
+}
+
+impl Archive {
+    pub fn new(config: &HistoryConfig) -> anyhow::Result<Self> {
+        let dir = dirs::data_dir()
+            .context("Could not find a data directory for the history")?
+            .join("cleave")
+            .join("history");
+        Ok(Self::in_dir(dir, config))
+    }
+
+    /// An archive kept in `dir` instead of the data directory.
+    pub fn in_dir(dir: PathBuf, config: &HistoryConfig) -> Self {
+        Self {
+            dir,
+            max_entries: config.max_entries,
+            max_age: (config.max_age_days > 0).then(|| DAY * config.max_age_days),
+        }
+    }
+
+    /// Stores a capture, then drops whatever is past the retention limits.
+    pub fn add(&self, image: &RgbaImage, monitor: &str, rect: Option<Rect>) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Could not create {}", self.dir.display()))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let sidecar = Sidecar {
+            timestamp,
+            monitor: monitor.to_string(),
+            rect,
+        };
+        let path = self.dir.join(format!("{}.png", timestamp));
+        image
+            .save(&path)
+            .with_context(|| format!("Could not save image to {}", path.display()))?;
+        std::fs::write(
+            path.with_extension("json"),
+            serde_json::to_string(&sidecar)?,
+        )?;
+        self.prune()
+    }
+
+    /// Every entry, newest first. Images without a readable sidecar are ignored.
+    pub fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let image = path.with_extension("png");
+            let Some(sidecar) = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+            else {
+                continue;
+            };
+            if image.exists() {
+                entries.push(Entry { image, sidecar });
+            }
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.sidecar.timestamp));
+        Ok(entries)
+    }
+
+    fn prune(&self) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        for (i, entry) in self.entries()?.iter().enumerate() {
+            let age = now.saturating_sub(Duration::from_millis(entry.sidecar.timestamp));
+            let too_many = self.max_entries > 0 && i >= self.max_entries;
+            if too_many || self.max_age.is_some_and(|max| age > max) {
+                entry.delete()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// A fresh archive in a temporary directory, with the entries `ages` old.
+    fn archive(name: &str, config: &HistoryConfig, ages: &[Duration]) -> Archive {
+        let dir = std::env::temp_dir().join(format!("cleave-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        for age in ages {
+            let timestamp = (now - *age).as_millis() as u64;
+            let path = dir.join(format!("{}.png", timestamp));
+            RgbaImage::new(1, 1).save(&path).unwrap();
+            let sidecar = Sidecar {
+                timestamp,
+                monitor: "DP-1".to_string(),
+                rect: None,
+            };
+            let json = serde_json::to_string(&sidecar).unwrap();
+            std::fs::write(path.with_extension("json"), json).unwrap();
+        }
+        Archive::in_dir(dir, config)
+    }
+
+    fn ages(archive: &Archive) -> Vec<u64> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        archive
+            .entries()
+            .unwrap()
+            .iter()
+            .map(|e| (now - Duration::from_millis(e.sidecar.timestamp)).as_secs() / 3600)
+            .collect()
+    }
+
+    fn hours(hours: &[u64]) -> Vec<Duration> {
+        hours
+            .iter()
+            .map(|h| Duration::from_secs(h * 3600))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_entries() {
+        let config = HistoryConfig {
+            enabled: true,
+            max_entries: 2,
+            max_age_days: 0,
+        };
+        let archive = archive("history-count", &config, &hours(&[5, 1, 3000, 2]));
+        archive.prune().unwrap();
+        assert_eq!(ages(&archive), [1, 2]);
+        std::fs::remove_dir_all(&archive.dir).unwrap();
+    }
+
+    #[test]
+    fn drops_old_entries() {
+        let config = HistoryConfig {
+            enabled: true,
+            max_entries: 0,
+            max_age_days: 2,
+        };
+        let archive = archive("history-age", &config, &hours(&[1, 47, 49, 3000]));
+        archive.prune().unwrap();
+        assert_eq!(ages(&archive), [1, 47]);
+
+        // Adding an entry prunes too
+        let image = RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 255]));
+        archive.add(&image, "DP-2", None).unwrap();
+        let entries = archive.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].sidecar.monitor, "DP-2");
+        std::fs::remove_dir_all(&archive.dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_everything_without_limits() {
+        let config = HistoryConfig {
+            enabled: true,
+            max_entries: 0,
+            max_age_days: 0,
+        };
+        let archive = archive("history-unlimited", &config, &hours(&[1, 3000, 90000]));
+        archive.prune().unwrap();
+        assert_eq!(ages(&archive), [1, 3000, 90000]);
+        std::fs::remove_dir_all(&archive.dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+
+use image::{imageops, Rgba, RgbaImage};
+use serde::{de, Deserialize, Deserializer};
+
+/// A color written as `#rrggbb`, `#rrggbbaa` or `transparent`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub Rgba<u8>);
+
+impl Color {
+    fn parse(s: &str) -> Option<Self> {
+        if s == "transparent" {
+            return Some(Self(Rgba([0, 0, 0, 0])));
+        }
+        let hex = s.strip_prefix('#')?;
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        match hex.len() {
+            6 => Some(Self(Rgba([channel(0)?, channel(2)?, channel(4)?, 255]))),
+            8 => Some(Self(Rgba([
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+                channel(6)?,
+            ]))),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::parse(&s).ok_or_else(|| de::Error::custom(format!("invalid color {:?}", s)))
+    }
+}
+
+/// What the capture is placed on.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(untagged)]
+pub enum Background {
+    Solid(Color),
+    /// From the top left to the bottom right corner
+    Gradient([Color; 2]),
+}
+
+/// How a capture is dressed up before it is exported.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Style {
+    pub padding: u32, // Space around the capture, in pixels
+    pub background: Background,
+    pub radius: u32,         // Of the capture's corners
+    pub shadow: f32,         // Blur radius of the drop shadow, 0 for none
+    pub shadow_offset: i32,  // How far the shadow falls below the capture
+    pub shadow_opacity: f32, // Between 0 and 1
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            padding: 48,
+            background: Background::Solid(Color(Rgba([241, 243, 245, 255]))),
+            radius: 10,
+            shadow: 16.0,
+            shadow_offset: 8,
+            shadow_opacity: 0.35,
+        }
+    }
+}
+
+/// The styles that come with cleave, used when the config doesn't define any.
+pub fn builtin_styles() -> BTreeMap<String, Style> {
+    let gradient = |from: [u8; 3], to: [u8; 3]| {
+        Background::Gradient([
+            Color(Rgba([from[0], from[1], from[2], 255])),
+            Color(Rgba([to[0], to[1], to[2], 255])),
+        ])
+    };
+    BTreeMap::from([
+        ("light".to_string(), Style::default()),
+        (
+            "ocean".to_string(),
+            Style {
+                padding: 64,
+                background: gradient([79, 172, 254], [0, 242, 254]),
+                ..Style::default()
+            },
+        ),
+        (
+            "sunset".to_string(),
+            Style {
+                padding: 64,
+                background: gradient([250, 112, 154], [254, 225, 64]),
+                ..Style::default()
+            },
+        ),
+        (
+            "shadow".to_string(),
+            Style {
+                padding: 32,
+                background: Background::Solid(Color(Rgba([0, 0, 0, 0]))),
+                ..Style::default()
+            },
+        ),
+    ])
+}
+
+/// Places `image` on its background with rounded corners and a drop shadow.
+pub fn apply(image: &RgbaImage, style: &Style) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let (canvas_width, canvas_height) = (width + 2 * style.padding, height + 2 * style.padding);
+    let mut canvas = background(canvas_width, canvas_height, style.background);
+
+    let mut content = image.clone();
+    round_corners(&mut content, style.radius);
+
+    if style.shadow > 0.0 && style.shadow_opacity > 0.0 {
+        // The shadow takes the shape of the capture, including transparent parts of it
+        let opacity = style.shadow_opacity.clamp(0.0, 1.0);
+        let mut shadow = RgbaImage::new(canvas_width, canvas_height);
+        for (x, y, pixel) in content.enumerate_pixels() {
+            let alpha = (pixel[3] as f32 * opacity) as u8;
+            let y = y as i64 + style.padding as i64 + style.shadow_offset as i64;
+            if (0..canvas_height as i64).contains(&y) {
+                shadow.put_pixel(x + style.padding, y as u32, Rgba([0, 0, 0, alpha]));
+            }
+        }
+        let shadow = imageops::fast_blur(&shadow, style.shadow / 2.0);
+        imageops::overlay(&mut canvas, &shadow, 0, 0);
+    }
+
+    imageops::overlay(
+        &mut canvas,
+        &content,
+        style.padding as i64,
+        style.padding as i64,
+    );
+    canvas
+}
+
+fn background(width: u32, height: u32, background: Background) -> RgbaImage {
+    match background {
+        Background::Solid(Color(color)) => RgbaImage::from_pixel(width, height, color),
+        Background::Gradient([Color(from), Color(to)]) => {
+            let length = (width + height).saturating_sub(2).max(1) as f32;
+            RgbaImage::from_fn(width, height, |x, y| {
+                let t = (x + y) as f32 / length;
+                Rgba(std::array::from_fn(|i| {
+                    (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t).round() as u8
+                }))
+            })
+        }
+    }
+}
+
+/// Makes the corners transparent, with antialiased edges.
+fn round_corners(image: &mut RgbaImage, radius: u32) {
+    let (width, height) = image.dimensions();
+    let radius = radius.min(width / 2).min(height / 2);
+    if radius == 0 {
+        return;
+    }
+    let r = radius as f32;
+    for y in 0..radius {
+        for x in 0..radius {
+            // Distance from the centre of the corner's circle to the centre of the pixel
+            let (dx, dy) = (r - x as f32 - 0.5, r - y as f32 - 0.5);
+            let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+            let corners = [
+                (x, y),
+                (width - 1 - x, y),
+                (x, height - 1 - y),
+                (width - 1 - x, height - 1 - y),
+            ];
+            for (x, y) in corners {
+                let pixel = image.get_pixel_mut(x, y);
+                pixel[3] = (pixel[3] as f32 * coverage) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([200, 30, 30, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn plain(padding: u32, background: Background) -> Style {
+        Style {
+            padding,
+            background,
+            radius: 0,
+            shadow: 0.0,
+            shadow_offset: 0,
+            shadow_opacity: 0.0,
+        }
+    }
+
+    #[test]
+    fn pads_the_capture() {
+        let image = RgbaImage::from_pixel(30, 20, RED);
+        let styled = apply(&image, &plain(10, Background::Solid(Color(WHITE))));
+        assert_eq!(styled.dimensions(), (50, 40));
+        assert_eq!(*styled.get_pixel(5, 5), WHITE);
+        assert_eq!(*styled.get_pixel(10, 10), RED);
+        assert_eq!(*styled.get_pixel(39, 29), RED);
+        assert_eq!(*styled.get_pixel(40, 30), WHITE);
+        assert_eq!(
+            apply(&image, &plain(0, Background::Solid(Color(WHITE)))),
+            image
+        );
+    }
+
+    #[test]
+    fn makes_the_corners_transparent() {
+        let image = RgbaImage::from_pixel(30, 20, RED);
+        let transparent = Background::Solid(Color(Rgba([0, 0, 0, 0])));
+        let styled = apply(
+            &image,
+            &Style {
+                radius: 8,
+                ..plain(4, transparent)
+            },
+        );
+        for (x, y) in [(4, 4), (33, 4), (4, 23), (33, 23)] {
+            assert_eq!(styled.get_pixel(x, y)[3], 0, "{:?}", (x, y));
+        }
+        assert_eq!(*styled.get_pixel(19, 14), RED);
+        assert_eq!(*styled.get_pixel(12, 4), RED);
+    }
+
+    #[test]
+    fn spans_the_gradient_from_corner_to_corner() {
+        let (from, to) = (Rgba([0, 100, 200, 255]), Rgba([200, 100, 0, 255]));
+        let image = RgbaImage::from_pixel(10, 10, RED);
+        let styled = apply(
+            &image,
+            &plain(5, Background::Gradient([Color(from), Color(to)])),
+        );
+        assert_eq!(*styled.get_pixel(0, 0), from);
+        assert_eq!(*styled.get_pixel(19, 19), to);
+        assert_eq!(*styled.get_pixel(19, 0), Rgba([100, 100, 100, 255]));
+    }
+
+    #[test]
+    fn keeps_the_shadow_inside_the_canvas() {
+        let image = RgbaImage::from_pixel(20, 20, RED);
+        let shadow = |shadow_offset| Style {
+            shadow: 2.0,
+            shadow_offset,
+            shadow_opacity: 1.0,
+            ..plain(10, Background::Solid(Color(WHITE)))
+        };
+        // A shadow falling upwards darkens the padding above the capture, not below
+        let styled = apply(&image, &shadow(-6));
+        assert!(styled.get_pixel(20, 6)[0] < 200);
+        assert_eq!(*styled.get_pixel(20, 35), WHITE);
+        for offset in [-1000, 1000] {
+            let styled = apply(&image, &shadow(offset));
+            assert_eq!(styled.dimensions(), (40, 40));
+            assert_eq!(*styled.get_pixel(20, 20), RED);
+        }
+    }
+}
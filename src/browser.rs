@@ -0,0 +1,279 @@
+use std::{path::PathBuf, time::Instant};
+
+use anyhow::Context;
+use glam::DVec2;
+use image::{imageops, Rgba, RgbaImage};
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowAttributes, WindowId},
+};
+
+use cleave_graphics::prelude::*;
+
+use crate::{
+    archive::{Archive, Entry},
+    config::Config,
+    context::SelectionUniforms,
+    export,
+    outcome::Outcome,
+    pin::{self, DOUBLE_CLICK},
+};
+
+const THUMBNAIL: u32 = 160;
+const PADDING: u32 = 12;
+const CELL: u32 = THUMBNAIL + PADDING;
+const COLUMNS: u32 = 5;
+const MAX_ROWS: u32 = 4; // Visible at once, the rest is reached by scrolling
+const BORDER: u32 = 3;
+
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 36, 255]);
+const HIGHLIGHT: Rgba<u8> = Rgba([66, 135, 245, 255]);
+
+/// A window showing thumbnails of the capture history, to copy, save or delete old captures.
+pub struct HistoryBrowser {
+    graphics: Graphics<Window>,
+    bundle: GraphicsBundle<SelectionUniforms>,
+    size: PhysicalSize<u32>,
+    entries: Vec<Entry>,
+    thumbnails: Vec<RgbaImage>,
+    selected: usize,
+    scroll: u32, // First visible row
+    mouse_position: DVec2,
+    last_click: Option<Instant>,
+    /// What the browser was last used for, and where the capture was saved
+    pub result: Option<(Outcome, Option<PathBuf>)>,
+}
+
+impl HistoryBrowser {
+    pub fn new(event_loop: &ActiveEventLoop) -> anyhow::Result<Self> {
+        let config = Config::load_or_default();
+        let entries = Archive::new(&config.history)?.entries()?;
+        if entries.is_empty() {
+            anyhow::bail!("The capture history is empty");
+        }
+        let thumbnails = entries
+            .iter()
+            .map(|entry| {
+                let image = image::open(&entry.image)
+                    .with_context(|| format!("Could not open {}", entry.image.display()))?
+                    .to_rgba8();
+                let scale = (THUMBNAIL as f32 / image.width().max(image.height()) as f32).min(1.0);
+                let width = (image.width() as f32 * scale).max(1.0) as u32;
+                let height = (image.height() as f32 * scale).max(1.0) as u32;
+                Ok(imageops::thumbnail(&image, width, height))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let rows = (entries.len() as u32).div_ceil(COLUMNS).min(MAX_ROWS);
+        let size = PhysicalSize::new(COLUMNS * CELL + PADDING, rows * CELL + PADDING);
+        let window = event_loop.create_window(
+            WindowAttributes::default()
+                .with_inner_size(size)
+                .with_resizable(false)
+                .with_title("Cleave History"),
+        )?;
+        let graphics = Graphics::new(window, size.width, size.height);
+        let graphics = pollster::block_on(graphics)?;
+        let bundle = GraphicsBundle::new(
+            RgbaImage::from_pixel(size.width, size.height, BACKGROUND).into(),
+            &graphics.device,
+            &graphics.queue,
+            wgpu::PrimitiveTopology::TriangleStrip,
+            graphics.config.format,
+        );
+
+        let mut browser = Self {
+            graphics,
+            bundle,
+            size,
+            entries,
+            thumbnails,
+            selected: 0,
+            scroll: 0,
+            mouse_position: DVec2::ZERO,
+            last_click: None,
+            result: None,
+        };
+        browser.update_sheet();
+        Ok(browser)
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.graphics.id()
+    }
+
+    /// Returns `false` once the browser should be closed.
+    pub fn handle_event(&mut self, event: WindowEvent) -> bool {
+        match event {
+            WindowEvent::RedrawRequested => self.draw(),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = DVec2::new(position.x, position.y);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let Some(index) = self.entry_at(self.mouse_position) else {
+                    return true;
+                };
+                let double_click = self.last_click.is_some_and(|t| t.elapsed() < DOUBLE_CLICK);
+                if double_click && index == self.selected {
+                    return !self.copy();
+                }
+                self.last_click = Some(Instant::now());
+                self.select(index);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let rows = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / CELL as f32,
+                };
+                // Horizontal scrolling and touchpads report zero for the rows
+                if rows != 0.0 {
+                    let step = -rows.signum() as i64 * COLUMNS as i64;
+                    self.select((self.selected as i64 + step).max(0) as usize);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: key,
+                        ..
+                    },
+                ..
+            } => match key {
+                Key::Named(NamedKey::Escape) => return false,
+                Key::Named(NamedKey::Enter | NamedKey::Space) => return !self.copy(),
+                Key::Named(NamedKey::Delete | NamedKey::Backspace) => return self.delete(),
+                Key::Named(NamedKey::ArrowLeft) => self.select(self.selected.saturating_sub(1)),
+                Key::Named(NamedKey::ArrowRight) => self.select(self.selected + 1),
+                Key::Named(NamedKey::ArrowUp) => {
+                    self.select(self.selected.saturating_sub(COLUMNS as usize))
+                }
+                Key::Named(NamedKey::ArrowDown) => self.select(self.selected + COLUMNS as usize),
+                Key::Character(c) if c.eq_ignore_ascii_case("c") => return !self.copy(),
+                Key::Character(c) if c.eq_ignore_ascii_case("s") => self.save(),
+                _ => {}
+            },
+            WindowEvent::CloseRequested => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// Copies the selected capture to the clipboard, returns whether that worked.
+    fn copy(&mut self) -> bool {
+        let entry = &self.entries[self.selected];
+        let result = image::open(&entry.image)
+            .with_context(|| format!("Could not open {}", entry.image.display()))
+            .and_then(|image| export::copy(image.to_rgba8(), None));
+        match result {
+            Ok(()) => {
+                self.result = Some((Outcome::Copied, None));
+                true
+            }
+            Err(err) => {
+                eprintln!("Could not copy capture: {:?}", err);
+                false
+            }
+        }
+    }
+
+    /// Saves a copy of the selected capture to the pictures directory.
+    fn save(&mut self) {
+        let entry = &self.entries[self.selected];
+        let Some(dir) = dirs::picture_dir().or_else(dirs::home_dir) else {
+            eprintln!("Could not find a directory to save to");
+            return;
+        };
+        let path = dir.join(format!("cleave-{}.png", entry.sidecar.timestamp));
+        match std::fs::copy(&entry.image, &path) {
+            Ok(_) => {
+                eprintln!("Saved capture to {}", path.display());
+                self.result = Some((Outcome::Saved, Some(path)));
+            }
+            Err(err) => eprintln!("Could not save capture to {}: {:?}", path.display(), err),
+        }
+    }
+
+    /// Deletes the selected capture, returns `false` once there is nothing left to show.
+    fn delete(&mut self) -> bool {
+        if let Err(err) = self.entries[self.selected].delete() {
+            eprintln!("Could not delete capture: {:?}", err);
+            return true;
+        }
+        self.entries.remove(self.selected);
+        self.thumbnails.remove(self.selected);
+        if self.entries.is_empty() {
+            return false;
+        }
+        self.select(self.selected);
+        true
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len() - 1);
+        let row = self.selected as u32 / COLUMNS;
+        let visible_rows = self.size.height / CELL;
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + visible_rows {
+            self.scroll = row + 1 - visible_rows;
+        }
+        self.update_sheet();
+    }
+
+    fn entry_at(&self, position: DVec2) -> Option<usize> {
+        let column = (position.x as u32).saturating_sub(PADDING / 2) / CELL;
+        let row = (position.y as u32).saturating_sub(PADDING / 2) / CELL + self.scroll;
+        let index = (row * COLUMNS + column) as usize;
+        (column < COLUMNS && index < self.entries.len()).then_some(index)
+    }
+
+    /// Lays the visible thumbnails out in a grid and uploads it as the window's texture.
+    fn update_sheet(&mut self) {
+        let mut sheet = RgbaImage::from_pixel(self.size.width, self.size.height, BACKGROUND);
+        let first = (self.scroll * COLUMNS) as usize;
+        for (i, thumbnail) in self.thumbnails.iter().enumerate().skip(first) {
+            let cell = (i - first) as u32;
+            let (x, y) = (
+                PADDING + cell % COLUMNS * CELL,
+                PADDING + cell / COLUMNS * CELL,
+            );
+            if y >= self.size.height {
+                break;
+            }
+            if i == self.selected {
+                let highlight = RgbaImage::from_pixel(
+                    THUMBNAIL + 2 * BORDER,
+                    THUMBNAIL + 2 * BORDER,
+                    HIGHLIGHT,
+                );
+                imageops::replace(
+                    &mut sheet,
+                    &highlight,
+                    (x - BORDER) as i64,
+                    (y - BORDER) as i64,
+                );
+                let inner = RgbaImage::from_pixel(THUMBNAIL, THUMBNAIL, BACKGROUND);
+                imageops::replace(&mut sheet, &inner, x as i64, y as i64);
+            }
+            // Centered in its cell
+            let dx = (THUMBNAIL - thumbnail.width()) / 2;
+            let dy = (THUMBNAIL - thumbnail.height()) / 2;
+            imageops::overlay(&mut sheet, thumbnail, (x + dx) as i64, (y + dy) as i64);
+        }
+        self.bundle
+            .set_texture(sheet.into(), &self.graphics.device, &self.graphics.queue);
+        self.graphics.request_redraw();
+    }
+
+    fn draw(&mut self) {
+        pin::draw(&mut self.graphics, &self.bundle);
+    }
+}
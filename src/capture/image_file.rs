@@ -0,0 +1,81 @@
+use std::{io::Read, path::Path, sync::Arc};
+
+use anyhow::Context;
+use image::RgbaImage;
+
+use super::{CaptureBackend, Output, WindowInfo};
+
+/// Serves an existing image as if it was a single screen without any windows.
+/// Clones share the decoded image.
+#[derive(Clone)]
+pub struct ImageBackend {
+    name: String,
+    image: Arc<RgbaImage>,
+}
+
+impl std::fmt::Debug for ImageBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageBackend")
+            .field("name", &self.name)
+            .field("size", &self.image.dimensions())
+            .finish()
+    }
+}
+
+impl ImageBackend {
+    pub fn new(name: impl Into<String>, image: RgbaImage) -> Self {
+        Self {
+            name: name.into(),
+            image: Arc::new(image),
+        }
+    }
+
+    /// Opens an image file, `-` reads it from stdin.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if path == Path::new("-") {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            let image = image::load_from_memory(&bytes)?;
+            return Ok(Self::new("stdin", image.to_rgba8()));
+        }
+        let image =
+            image::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+        Ok(Self::new(path.display().to_string(), image.to_rgba8()))
+    }
+
+    pub fn clipboard() -> anyhow::Result<Self> {
+        let data = arboard::Clipboard::new()?
+            .get_image()
+            .context("The clipboard does not contain an image")?;
+        let image = RgbaImage::from_raw(
+            data.width as u32,
+            data.height as u32,
+            data.bytes.into_owned(),
+        )
+        .context("Invalid image on the clipboard")?;
+        Ok(Self::new("clipboard", image))
+    }
+}
+
+impl CaptureBackend for ImageBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>> {
+        Ok(vec![Output {
+            id: 0,
+            name: self.name.clone(),
+            x: 0,
+            y: 0,
+            width: self.image.width(),
+            height: self.image.height(),
+            scale_factor: 1.0,
+            primary: true,
+        }])
+    }
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn capture(&self, _output: &Output) -> anyhow::Result<RgbaImage> {
+        Ok(self.image.as_ref().clone())
+    }
+}
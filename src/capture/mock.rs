@@ -0,0 +1,98 @@
+use image::{Rgba, RgbaImage};
+
+use super::{CaptureBackend, Cursor, Output, WindowInfo};
+
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 800;
+
+/// A fake desktop that always looks the same, with a few solid windows on a gradient.
+pub struct MockBackend {
+    windows: Vec<WindowInfo>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        let window = |title: &str, x, y, width, height| WindowInfo {
+            title: title.to_string(),
+            app_name: "mock".to_string(),
+            x,
+            y,
+            width,
+            height,
+            minimized: false,
+        };
+        Self {
+            windows: vec![
+                window("Editor", 40, 40, 720, 480),
+                window("Terminal", 600, 360, 560, 360),
+                window("Chat", 880, 60, 320, 240),
+            ],
+        }
+    }
+}
+
+impl CaptureBackend for MockBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>> {
+        Ok(vec![Output {
+            id: 0,
+            name: "mock".to_string(),
+            x: 0,
+            y: 0,
+            width: WIDTH,
+            height: HEIGHT,
+            scale_factor: 1.0,
+            primary: true,
+        }])
+    }
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        Ok(self.windows.clone())
+    }
+
+    fn capture(&self, output: &Output) -> anyhow::Result<RgbaImage> {
+        let mut image = RgbaImage::from_fn(output.width, output.height, |x, y| {
+            let r = (x * 255 / output.width) as u8;
+            let b = (y * 255 / output.height) as u8;
+            Rgba([r, 64, b, 255])
+        });
+        // Later windows are on top
+        for (i, window) in self.windows.iter().enumerate() {
+            let shade = 200 - i as u8 * 40;
+            let (left, top) = (window.x as u32, window.y as u32);
+            let (right, bottom) = (left + window.width, top + window.height);
+            for y in top..bottom.min(output.height) {
+                for x in left..right.min(output.width) {
+                    let border = x == left || y == top || x + 1 == right || y + 1 == bottom;
+                    let color = if border {
+                        Rgba([20, 20, 20, 255])
+                    } else if y < top + 24 {
+                        Rgba([shade / 2, shade / 2, shade, 255]) // Title bar
+                    } else {
+                        Rgba([shade, shade, shade, 255])
+                    };
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    fn cursor(&self) -> anyhow::Result<Option<Cursor>> {
+        // A plain arrow, white with a black outline
+        let image = RgbaImage::from_fn(12, 18, |x, y| {
+            let edge = y * 2 / 3;
+            if x > edge || y > 16 {
+                Rgba([0, 0, 0, 0])
+            } else if x == 0 || x == edge || y == 16 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        Ok(Some(Cursor {
+            image,
+            x: 600,
+            y: 400,
+        }))
+    }
+}
@@ -0,0 +1,326 @@
+use std::{path::Path, time::SystemTime};
+
+use anyhow::Context;
+use glam::Vec2;
+use image::RgbaImage;
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    export::{self, Layout, Mask, Rect, Region},
+    snap::SnapTargets,
+};
+
+mod image_file;
+mod mock;
+#[cfg(target_os = "linux")]
+mod portal;
+mod screen;
+
+pub use image_file::ImageBackend;
+pub use mock::MockBackend;
+pub use screen::XcapBackend;
+
+/// A display, or anything else that can be captured as a whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub primary: bool,
+}
+
+/// A window on some output, in global coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    pub app_name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub minimized: bool,
+}
+
+/// The mouse pointer at the time of a capture.
+#[derive(Clone, Debug)]
+pub struct Cursor {
+    pub image: RgbaImage,
+    pub x: i32, // Position of the top left corner, not the hotspot
+    pub y: i32,
+}
+
+impl Cursor {
+    pub fn draw(&self, image: &mut RgbaImage) {
+        image::imageops::overlay(image, &self.image, self.x as i64, self.y as i64);
+    }
+}
+
+/// Enumerates outputs and windows, and captures frames of outputs.
+pub trait CaptureBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>>;
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>>;
+
+    fn capture(&self, output: &Output) -> anyhow::Result<RgbaImage>;
+
+    /// The pointer in global coordinates, if the backend can see it.
+    fn cursor(&self) -> anyhow::Result<Option<Cursor>> {
+        Ok(None)
+    }
+
+    fn primary_output(&self) -> anyhow::Result<Output> {
+        let outputs = self.outputs()?;
+        let primary = outputs.iter().position(|o| o.primary).unwrap_or(0);
+        outputs
+            .into_iter()
+            .nth(primary)
+            .context("Could not get primary monitor")
+    }
+}
+
+/// Where the image to select from comes from.
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// Capture the primary monitor
+    Screen,
+    /// An existing image, e.g. from a file or the clipboard. It is decoded once up front, as
+    /// stdin can only be read once and the clipboard may change in the meantime.
+    Image(ImageBackend),
+    /// A generated, deterministic screen for trying things out without a display
+    Mock,
+}
+
+impl Source {
+    /// Opens an image file, `-` reads it from stdin.
+    pub fn file(path: &Path) -> anyhow::Result<Self> {
+        Ok(Source::Image(ImageBackend::open(path)?))
+    }
+
+    /// Takes the image currently on the clipboard.
+    pub fn clipboard() -> anyhow::Result<Self> {
+        Ok(Source::Image(ImageBackend::clipboard()?))
+    }
+
+    /// The backend to capture from. On Linux the screen falls back to the desktop portal when
+    /// it can't be captured directly, as on most Wayland sessions.
+    pub fn backend(&self) -> anyhow::Result<Box<dyn CaptureBackend>> {
+        Ok(match self {
+            #[cfg(target_os = "linux")]
+            Source::Screen => Box::new(portal::FallbackBackend::new()),
+            #[cfg(not(target_os = "linux"))]
+            Source::Screen => Box::new(XcapBackend),
+            Source::Image(backend) => Box::new(backend.clone()),
+            Source::Mock => Box::new(MockBackend::new()),
+        })
+    }
+
+    /// Whether the overlay covers the screen, as opposed to showing the image in a window.
+    pub fn is_screen(&self) -> bool {
+        matches!(self, Source::Screen)
+    }
+}
+
+/// A screenshot along with what is needed to select from it.
+pub struct Capture {
+    pub monitor: String,
+    pub monitor_id: u32,
+    pub image: RgbaImage,
+    pub size: PhysicalSize<u32>,
+    pub snap_targets: SnapTargets,
+    pub cursor: Option<Cursor>, // Relative to the image, not drawn into it
+    pub windows: Vec<WindowInfo>, // Visible windows, relative to the image
+    pub scale_factor: f32,
+    pub captured_at: SystemTime,
+}
+
+impl Capture {
+    /// Captures the primary output of the source.
+    pub fn new(source: &Source) -> anyhow::Result<Self> {
+        Self::of_output(source, None)
+    }
+
+    /// Captures the output with the given id, or the primary one.
+    pub fn of_output(source: &Source, id: Option<u32>) -> anyhow::Result<Self> {
+        Self::from_backend(source.backend()?.as_ref(), id)
+    }
+
+    fn from_backend(backend: &dyn CaptureBackend, id: Option<u32>) -> anyhow::Result<Self> {
+        let output = find_output(backend, id)?;
+        let captured_at = SystemTime::now();
+        let image = backend.capture(&output)?;
+        let size = PhysicalSize::new(image.width(), image.height());
+
+        let windows: Vec<WindowInfo> = backend
+            .windows()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|w| !w.minimized)
+            .map(|w| WindowInfo {
+                x: w.x - output.x,
+                y: w.y - output.y,
+                ..w
+            })
+            .collect();
+        let mut snap_targets = SnapTargets::detect(&image);
+        for window in &windows {
+            let min = Vec2::new(window.x as f32, window.y as f32);
+            let size = Vec2::new(window.width as f32, window.height as f32);
+            snap_targets.add_rect(min, min + size);
+        }
+
+        let cursor = backend.cursor().unwrap_or_default().map(|cursor| Cursor {
+            x: cursor.x - output.x,
+            y: cursor.y - output.y,
+            ..cursor
+        });
+
+        Ok(Self {
+            monitor: output.name,
+            monitor_id: output.id,
+            image,
+            size,
+            snap_targets,
+            cursor,
+            windows,
+            scale_factor: output.scale_factor,
+            captured_at,
+        })
+    }
+
+    /// Crops a rectangle out of the capture, as if it was selected in the overlay.
+    pub fn region(&self, rect: Rect) -> anyhow::Result<RgbaImage> {
+        crop(&self.image, rect)
+    }
+}
+
+/// Captures one output over and over, without the extra work needed for the overlay.
+pub struct Recorder {
+    backend: Box<dyn CaptureBackend>,
+    output: Output,
+}
+
+impl Recorder {
+    pub fn new(source: &Source, id: Option<u32>) -> anyhow::Result<Self> {
+        let backend = source.backend()?;
+        let output = find_output(backend.as_ref(), id)?;
+        Ok(Self { backend, output })
+    }
+
+    pub fn frame(&self, rect: Rect) -> anyhow::Result<RgbaImage> {
+        crop(&self.backend.capture(&self.output)?, rect)
+    }
+}
+
+/// The output with the given id, or the primary one.
+fn find_output(backend: &dyn CaptureBackend, id: Option<u32>) -> anyhow::Result<Output> {
+    match id {
+        Some(id) => backend
+            .outputs()?
+            .into_iter()
+            .find(|o| o.id == id)
+            .with_context(|| format!("Monitor {} is not connected", id)),
+        None => backend.primary_output(),
+    }
+}
+
+fn crop(image: &RgbaImage, rect: Rect) -> anyhow::Result<RgbaImage> {
+    let region = Region {
+        rect,
+        mask: Mask::None,
+    };
+    export::compose(image, &[region], Layout::Original)
+        .filter(|image| image.width() > 0 && image.height() > 0)
+        .context("The region is outside of the captured monitor")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::Rgba;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn crops_image_files() {
+        // Red, green, blue and white quadrants of 32x24 pixels each
+        let source = Source::file(&fixture("quadrants.png")).unwrap();
+        let capture = Capture::new(&source).unwrap();
+        assert_eq!(capture.size, PhysicalSize::new(64, 48));
+        assert_eq!(capture.monitor_id, 0);
+        // The image is decoded once and served again, which matters for stdin
+        assert_eq!(Capture::new(&source).unwrap().image, capture.image);
+
+        let rect = Rect {
+            x: 24,
+            y: 16,
+            width: 16,
+            height: 16,
+        };
+        let image = capture.region(rect).unwrap();
+        assert_eq!(image.dimensions(), (16, 16));
+        assert_eq!(*image.get_pixel(7, 7), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(8, 7), Rgba([0, 255, 0, 255]));
+        assert_eq!(*image.get_pixel(7, 8), Rgba([0, 0, 255, 255]));
+        assert_eq!(*image.get_pixel(8, 8), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn rejects_regions_outside_the_image() {
+        let capture = Capture::new(&Source::file(&fixture("quadrants.png")).unwrap()).unwrap();
+        let rect = Rect {
+            x: 64,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(capture.region(rect).is_err());
+    }
+
+    #[test]
+    fn captures_the_mock_screen() {
+        let capture = Capture::of_output(&Source::Mock, None).unwrap();
+        assert_eq!(capture.monitor, "mock");
+        assert_eq!(capture.size, PhysicalSize::new(1280, 800));
+        let titles: Vec<_> = capture.windows.iter().map(|w| w.title.as_str()).collect();
+        assert_eq!(titles, ["Editor", "Terminal", "Chat"]);
+        let cursor = capture.cursor.unwrap();
+        assert_eq!((cursor.x, cursor.y), (600, 400));
+        // Window borders are snap targets
+        let snapped = capture.snap_targets.snap(Vec2::new(38.0, 38.0), 4.0);
+        assert_eq!(snapped, Vec2::new(40.0, 40.0));
+
+        let error = Capture::of_output(&Source::Mock, Some(1)).err().unwrap();
+        assert_eq!(error.to_string(), "Monitor 1 is not connected");
+    }
+
+    #[test]
+    fn records_frames_of_the_mock_screen() {
+        let recorder = Recorder::new(&Source::Mock, Some(0)).unwrap();
+        // Across the bottom of the title bar of the chat window
+        let rect = Rect {
+            x: 900,
+            y: 80,
+            width: 10,
+            height: 8,
+        };
+        let frame = recorder.frame(rect).unwrap();
+        assert_eq!(frame.dimensions(), (10, 8));
+        assert_eq!(*frame.get_pixel(0, 0), Rgba([60, 60, 120, 255]));
+        assert_eq!(*frame.get_pixel(0, 7), Rgba([120, 120, 120, 255]));
+        assert_eq!(recorder.frame(rect).unwrap(), frame);
+
+        let outside = Rect { x: 1280, ..rect };
+        assert!(recorder.frame(outside).is_err());
+    }
+}
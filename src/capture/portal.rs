@@ -0,0 +1,359 @@
+use std::{
+    cell::{Cell, OnceCell},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use dbus::{
+    arg::{prop_cast, PropMap, Variant},
+    blocking::Connection,
+    message::MatchRule,
+};
+use image::RgbaImage;
+
+use super::{CaptureBackend, Cursor, Output, WindowInfo, XcapBackend};
+
+const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREENSHOT_INTERFACE: &str = "org.freedesktop.portal.Screenshot";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+
+/// How long to wait for the user to allow the screenshot in the portal dialog.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Captures the screen directly, or through the desktop portal once that fails, as on most
+/// Wayland sessions.
+pub struct FallbackBackend {
+    portal: OnceCell<PortalBackend>,
+}
+
+impl FallbackBackend {
+    pub fn new() -> Self {
+        Self {
+            portal: OnceCell::new(),
+        }
+    }
+
+    fn portal(&self, err: anyhow::Error) -> anyhow::Result<&PortalBackend> {
+        if let Some(portal) = self.portal.get() {
+            return Ok(portal);
+        }
+        eprintln!(
+            "Could not capture the screen, trying the desktop portal: {:#}",
+            err
+        );
+        let portal = PortalBackend::new()?;
+        Ok(self.portal.get_or_init(|| portal))
+    }
+}
+
+impl CaptureBackend for FallbackBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>> {
+        match self.portal.get() {
+            Some(portal) => portal.outputs(),
+            None => XcapBackend
+                .outputs()
+                .or_else(|err| self.portal(err)?.outputs()),
+        }
+    }
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        match self.portal.get() {
+            Some(portal) => portal.windows(),
+            None => XcapBackend.windows(),
+        }
+    }
+
+    fn capture(&self, output: &Output) -> anyhow::Result<RgbaImage> {
+        match self.portal.get() {
+            Some(portal) => portal.capture(output),
+            None => XcapBackend
+                .capture(output)
+                .or_else(|err| self.portal(err)?.capture(output)),
+        }
+    }
+
+    fn cursor(&self) -> anyhow::Result<Option<Cursor>> {
+        match self.portal.get() {
+            Some(_) => Ok(None), // Not in the screenshot and not known to the portal
+            None => XcapBackend.cursor(),
+        }
+    }
+}
+
+/// Serves screenshots of the whole desktop from the portal as a single output without windows.
+pub struct PortalBackend {
+    connection: Connection,
+    first: Cell<Option<RgbaImage>>, // Taken to learn the size of the desktop, served once
+    width: u32,
+    height: u32,
+}
+
+impl PortalBackend {
+    pub fn new() -> anyhow::Result<Self> {
+        let connection =
+            Connection::new_session().context("Could not connect to the session bus")?;
+        let image = screenshot(&connection)?;
+        Ok(Self {
+            connection,
+            width: image.width(),
+            height: image.height(),
+            first: Cell::new(Some(image)),
+        })
+    }
+}
+
+impl CaptureBackend for PortalBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>> {
+        Ok(vec![Output {
+            id: 0,
+            name: "portal".to_string(),
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+            scale_factor: 1.0,
+            primary: true,
+        }])
+    }
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn capture(&self, _output: &Output) -> anyhow::Result<RgbaImage> {
+        match self.first.take() {
+            Some(image) => Ok(image),
+            None => screenshot(&self.connection),
+        }
+    }
+}
+
+/// Takes a screenshot of the whole desktop through `org.freedesktop.portal.Screenshot`.
+///
+/// This works on Wayland sessions where capturing monitors directly is not allowed.
+fn screenshot(connection: &Connection) -> anyhow::Result<RgbaImage> {
+    static REQUESTS: AtomicU32 = AtomicU32::new(0);
+
+    // Subscribe before calling so the response can't be missed, the request path is predictable
+    let token = format!(
+        "cleave{}_{}",
+        std::process::id(),
+        REQUESTS.fetch_add(1, Ordering::Relaxed)
+    );
+    let sender = connection
+        .unique_name()
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request_path = format!("{}/request/{}/{}", OBJECT_PATH, sender, token);
+    let response = Arc::new(Mutex::new(None));
+    let slot = response.clone();
+    let rule = MatchRule::new_signal(REQUEST_INTERFACE, "Response")
+        .with_path(dbus::Path::new(request_path).map_err(anyhow::Error::msg)?);
+    connection.add_match(rule, move |result: (u32, PropMap), _, _| {
+        *slot.lock().unwrap() = Some(result);
+        false
+    })?;
+
+    let mut options = PropMap::new();
+    options.insert("handle_token".to_string(), Variant(Box::new(token)));
+    options.insert("interactive".to_string(), Variant(Box::new(false)));
+    let proxy = connection.with_proxy(DESTINATION, OBJECT_PATH, Duration::from_secs(5));
+    let _: (dbus::Path,) = proxy
+        .method_call(SCREENSHOT_INTERFACE, "Screenshot", ("", options))
+        .context("The desktop portal does not support screenshots")?;
+
+    let started = Instant::now();
+    let (code, results) = loop {
+        if let Some(result) = response.lock().unwrap().take() {
+            break result;
+        }
+        if started.elapsed() > RESPONSE_TIMEOUT {
+            anyhow::bail!("The desktop portal did not answer the screenshot request");
+        }
+        connection.process(Duration::from_millis(100))?;
+    };
+    match code {
+        0 => {}
+        1 => anyhow::bail!("The screenshot was denied"),
+        _ => anyhow::bail!("The desktop portal could not take a screenshot"),
+    }
+
+    let uri = prop_cast::<String>(&results, "uri").context("The portal returned no screenshot")?;
+    let path = file_path(uri).with_context(|| format!("Unsupported screenshot uri {}", uri))?;
+    let image = image::open(&path)
+        .with_context(|| format!("Could not open {}", path.display()))?
+        .to_rgba8();
+    // Some portals save into a temporary file that nobody else cleans up, others into the
+    // user's pictures, which are theirs to keep
+    if is_temporary(&path) {
+        let _ = std::fs::remove_file(&path);
+    }
+    Ok(image)
+}
+
+fn is_temporary(path: &Path) -> bool {
+    path.starts_with(std::env::temp_dir())
+}
+
+/// Turns a `file://` uri into a path, decoding percent escapes.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    use dbus::{
+        arg::RefArg,
+        channel::{Channel, MatchingReceiver, Sender},
+        Message,
+    };
+    use image::Rgba;
+
+    use super::*;
+
+    /// A private session bus that goes away when dropped.
+    struct Bus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl Bus {
+        /// Starts `dbus-daemon`, or returns `None` if it isn't installed.
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        fn connect(&self) -> Connection {
+            let mut channel = Channel::open_private(&self.address).unwrap();
+            channel.register().unwrap();
+            Connection::from(channel)
+        }
+
+        /// Answers every screenshot request like a portal would, with `code` and `uri`.
+        fn serve_portal(&self, code: u32, uri: &str) {
+            let service = self.connect();
+            service
+                .request_name(DESTINATION, false, true, false)
+                .unwrap();
+            let uri = uri.to_string();
+            service.start_receive(
+                MatchRule::new_method_call(),
+                Box::new(move |call, connection| {
+                    let (_, options): (&str, PropMap) = call.read2().unwrap();
+                    let token = options["handle_token"].as_str().unwrap();
+                    let sender = call.sender().unwrap();
+                    let sender = sender.trim_start_matches(':').replace('.', "_");
+                    let path = format!("{}/request/{}/{}", OBJECT_PATH, sender, token);
+                    let handle = dbus::Path::new(path.clone()).unwrap();
+                    let _ = connection.send(call.method_return().append1(handle));
+
+                    let mut results = PropMap::new();
+                    results.insert("uri".to_string(), Variant(Box::new(uri.clone())));
+                    let response = Message::new_signal(path, REQUEST_INTERFACE, "Response")
+                        .unwrap()
+                        .append2(code, results);
+                    let _ = connection.send(response);
+                    true
+                }),
+            );
+            // Until the bus shuts down
+            std::thread::spawn(move || while service.process(Duration::from_secs(1)).is_ok() {});
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    #[test]
+    fn takes_screenshots_through_the_portal() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("Skipping portal test, dbus-daemon is not installed");
+            return;
+        };
+        let dir = std::env::temp_dir().join(format!("cleave-portal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Screenshot 1.png");
+        let expected = RgbaImage::from_fn(8, 6, |x, y| Rgba([x as u8 * 30, y as u8 * 40, 0, 255]));
+        expected.save(&path).unwrap();
+        let uri = format!("file://{}", path.display()).replace(' ', "%20");
+        bus.serve_portal(0, &uri);
+
+        let connection = bus.connect();
+        assert_eq!(screenshot(&connection).unwrap(), expected);
+        // Temporary screenshots are cleaned up
+        assert!(!path.exists());
+        // Later requests get their own handle and work just the same
+        expected.save(&path).unwrap();
+        assert_eq!(screenshot(&connection).unwrap(), expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_denied_screenshots() {
+        let Some(bus) = Bus::start() else {
+            eprintln!("Skipping portal test, dbus-daemon is not installed");
+            return;
+        };
+        bus.serve_portal(1, "");
+        let err = screenshot(&bus.connect()).unwrap_err();
+        assert_eq!(err.to_string(), "The screenshot was denied");
+    }
+
+    #[test]
+    fn only_removes_temporary_screenshots() {
+        assert!(is_temporary(&std::env::temp_dir().join("screenshot.png")));
+        assert!(!is_temporary(Path::new(
+            "/home/user/Pictures/Screenshots/screenshot.png"
+        )));
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(
+            file_path("file:///tmp/Screenshot%20from%202024.png"),
+            Some(PathBuf::from("/tmp/Screenshot from 2024.png"))
+        );
+        assert_eq!(file_path("https://example.com/shot.png"), None);
+    }
+}
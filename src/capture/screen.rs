@@ -0,0 +1,92 @@
+use image::RgbaImage;
+
+use super::{CaptureBackend, Cursor, Output, WindowInfo};
+
+/// Captures the real screen.
+pub struct XcapBackend;
+
+impl CaptureBackend for XcapBackend {
+    fn outputs(&self) -> anyhow::Result<Vec<Output>> {
+        Ok(xcap::Monitor::all()?
+            .iter()
+            .map(|monitor| Output {
+                id: monitor.id(),
+                name: monitor.name().to_string(),
+                x: monitor.x(),
+                y: monitor.y(),
+                width: monitor.width(),
+                height: monitor.height(),
+                scale_factor: monitor.scale_factor(),
+                primary: monitor.is_primary(),
+            })
+            .collect())
+    }
+
+    fn windows(&self) -> anyhow::Result<Vec<WindowInfo>> {
+        Ok(xcap::Window::all()?
+            .iter()
+            .map(|window| WindowInfo {
+                title: window.title().to_string(),
+                app_name: window.app_name().to_string(),
+                x: window.x(),
+                y: window.y(),
+                width: window.width(),
+                height: window.height(),
+                minimized: window.is_minimized(),
+            })
+            .collect())
+    }
+
+    fn capture(&self, output: &Output) -> anyhow::Result<RgbaImage> {
+        let monitor = xcap::Monitor::all()?
+            .into_iter()
+            .find(|m| m.id() == output.id)
+            .ok_or_else(|| anyhow::anyhow!("Monitor {} is gone", output.name))?;
+        Ok(monitor.capture_image()?)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn cursor(&self) -> anyhow::Result<Option<Cursor>> {
+        x11_cursor()
+    }
+}
+
+/// Asks the X server for the pointer image, which xcap leaves out of captures.
+#[cfg(target_os = "linux")]
+fn x11_cursor() -> anyhow::Result<Option<Cursor>> {
+    use anyhow::Context;
+    use xcb::xfixes;
+
+    // XWayland only knows about the pointer while it is over X clients
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return Ok(None);
+    }
+    let (connection, _) =
+        xcb::Connection::connect_with_extensions(None, &[xcb::Extension::XFixes], &[])?;
+    connection.wait_for_reply(connection.send_request(&xfixes::QueryVersion {
+        client_major_version: 4,
+        client_minor_version: 0,
+    }))?;
+    let reply = connection.wait_for_reply(connection.send_request(&xfixes::GetCursorImage {}))?;
+
+    // Pixels are premultiplied ARGB
+    let pixels = reply
+        .cursor_image()
+        .iter()
+        .flat_map(|pixel| {
+            let [b, g, r, a] = pixel.to_le_bytes();
+            let unpremultiply = |c: u8| match a {
+                0 => 0,
+                a => (c as u32 * 255 / a as u32).min(255) as u8,
+            };
+            [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+        })
+        .collect();
+    let image = RgbaImage::from_raw(reply.width() as u32, reply.height() as u32, pixels)
+        .context("Invalid cursor image")?;
+    Ok(Some(Cursor {
+        image,
+        x: reply.x() as i32 - reply.xhot() as i32,
+        y: reply.y() as i32 - reply.yhot() as i32,
+    }))
+}
@@ -0,0 +1,256 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::{ArgGroup, Parser, Subcommand};
+
+use crate::scale::{Filter, Scaling};
+
+// Capturing more often than this would be a recording
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A lightweight, GPU-accelerated screen capture tool
+#[derive(Parser, Debug)]
+#[command(version, about)]
+#[command(group(ArgGroup::new("region").args(["last", "preset"])))]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub action: Option<Action>,
+
+    /// Select from this image instead of capturing the screen, `-` reads it from stdin
+    #[arg(value_name = "IMAGE", conflicts_with = "daemon")]
+    pub image: Option<PathBuf>,
+
+    /// Select from the image on the clipboard and put the result back on it
+    #[arg(long, conflicts_with_all = ["daemon", "image"])]
+    pub clipboard: bool,
+
+    /// Capture the region that was captured last again, without showing the overlay
+    #[arg(long, conflicts_with_all = ["daemon", "history"])]
+    pub last: bool,
+
+    /// Capture the preset with this name straight away, without showing the overlay
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["daemon", "history", "last"])]
+    pub preset: Option<String>,
+
+    /// Capture the `--last` or `--preset` region every SECONDS until interrupted, writing
+    /// numbered frames into the `-o` directory
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "region",
+        conflicts_with = "stdout",
+        value_parser = parse_interval
+    )]
+    pub timelapse: Option<Duration>,
+
+    /// Stop the timelapse after this many seconds
+    #[arg(long, value_name = "SECONDS", requires = "timelapse", value_parser = parse_seconds)]
+    pub duration: Option<Duration>,
+
+    /// Also join the timelapse frames into an animation
+    #[arg(long, requires = "timelapse")]
+    pub animate: bool,
+
+    /// Also save the selection as a preset with this name, replacing any existing one
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["daemon", "history", "last", "preset"])]
+    pub save_preset: Option<String>,
+
+    /// Save the preset relative to the window under the selection, so it follows the window
+    #[arg(long, requires = "save_preset")]
+    pub relative: bool,
+
+    /// Browse the history of recent captures instead of taking a new one
+    #[arg(long, conflicts_with_all = ["daemon", "image", "clipboard"])]
+    pub history: bool,
+
+    /// Select from a generated, always identical fake screen instead of capturing the real one
+    #[arg(long, hide = true, conflicts_with_all = ["daemon", "image", "clipboard", "history"])]
+    pub mock: bool,
+
+    /// Stay running in the background with the GPU set up, so captures open instantly.
+    /// Further invocations of cleave trigger a capture in the daemon.
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Send a JSON command to the running daemon and print its JSON reply,
+    /// e.g. '{"cmd": "set_selection", "x": 0, "y": 0, "width": 640, "height": 480}'
+    #[arg(long, value_name = "JSON", conflicts_with = "daemon")]
+    pub ctl: Option<String>,
+
+    /// Save the selection to this file instead of copying it to the clipboard,
+    /// the format is taken from the extension. `-` writes a PNG to stdout
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Write the selection as PNG to stdout, same as `-o -`
+    #[arg(long, conflicts_with = "output")]
+    pub stdout: bool,
+
+    /// Also copy the selection to the clipboard when saving it
+    #[arg(short, long)]
+    pub copy: bool,
+
+    /// Scale the exported image by this factor
+    #[arg(long, value_name = "FACTOR", value_parser = parse_factor)]
+    pub scale: Option<f32>,
+
+    /// Shrink the exported image until its longer side is at most this many pixels
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_size: Option<u32>,
+
+    /// How pixels are resampled when scaling
+    #[arg(long, value_enum, default_value_t)]
+    pub filter: Filter,
+
+    /// Export in logical pixels, shrinking captures of HiDPI monitors by their scale factor
+    #[arg(long)]
+    pub logical: bool,
+
+    /// Don't embed the time, monitor and region of the capture into saved or copied PNGs
+    #[arg(long)]
+    pub strip_metadata: bool,
+
+    /// Print the result of the capture as JSON on stdout, or stderr if the image goes to stdout.
+    /// The exit code is 0 when copied, 1 when cancelled, 2 on failure, 3 when saved and 4 when pinned
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// Print the metadata cleave embedded into a PNG
+    Inspect {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+impl Args {
+    /// Whether a running daemon can take this capture. It captures the screen and delivers the
+    /// selection with its own options, so not if any of them were set for this invocation.
+    pub fn forwardable(&self) -> bool {
+        let source = self.image.is_some()
+            || self.clipboard
+            || self.mock
+            || self.history
+            || self.last
+            || self.preset.is_some()
+            || self.save_preset.is_some();
+        // The daemon can't write to this process's stdout, so `-o -` and `--stdout` stay here too
+        let delivery = self.output.is_some()
+            || self.stdout
+            || self.json
+            || self.copy
+            || self.scale.is_some()
+            || self.max_size.is_some()
+            || self.logical
+            || self.strip_metadata;
+        !self.daemon && !source && !delivery
+    }
+
+    /// Where the selection is saved, `-` for stdout.
+    pub fn output(&self) -> Option<PathBuf> {
+        if self.stdout {
+            Some(PathBuf::from("-"))
+        } else {
+            self.output.clone()
+        }
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        Scaling {
+            factor: self.scale,
+            max_size: self.max_size,
+            filter: self.filter,
+            logical: self.logical,
+        }
+    }
+}
+
+fn parse_factor(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(factor) if factor > 0.0 && factor.is_finite() => Ok(factor),
+        _ => Err(format!("{} is not a positive number", s)),
+    }
+}
+
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let interval = parse_seconds(s)?;
+    if interval < MIN_INTERVAL {
+        return Err(format!(
+            "{} is shorter than the minimum of {} seconds",
+            s,
+            MIN_INTERVAL.as_secs_f32()
+        ));
+    }
+    Ok(interval)
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 => {
+            Duration::try_from_secs_f64(seconds).map_err(|_| format!("{} is too long", s))
+        }
+        _ => Err(format!("{} is not a positive number of seconds", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("cleave").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn forwards_plain_captures_only() {
+        assert!(parse(&[]).forwardable());
+        for args in [
+            &["--daemon"][..],
+            &["--clipboard"],
+            &["--last"],
+            &["-o", "shot.png"],
+            &["-o", "-"],
+            &["--stdout"],
+            &["--json"],
+            &["--copy"],
+            &["--scale", "0.5"],
+            &["--max-size", "800"],
+            &["--logical"],
+            &["--strip-metadata"],
+        ] {
+            assert!(!parse(args).forwardable(), "{:?} was forwarded", args);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_factors() {
+        assert!(Args::try_parse_from(["cleave", "--scale", "0"]).is_err());
+        assert!(Args::try_parse_from(["cleave", "--scale", "inf"]).is_err());
+        assert_eq!(parse(&["--scale", "2"]).scale, Some(2.0));
+    }
+
+    #[test]
+    fn rejects_invalid_seconds() {
+        for seconds in ["-5", "0", "NaN", "inf", "1e300"] {
+            assert!(Args::try_parse_from(["cleave", "--last", "--timelapse", seconds]).is_err());
+            let args = [
+                "cleave",
+                "--last",
+                "--timelapse",
+                "5",
+                "--duration",
+                seconds,
+            ];
+            assert!(Args::try_parse_from(args).is_err());
+        }
+        assert!(Args::try_parse_from(["cleave", "--last", "--timelapse", "0.01"]).is_err());
+        let args = parse(&["--last", "--timelapse", "0.1", "--duration", "0.05"]);
+        assert_eq!(args.timelapse, Some(MIN_INTERVAL));
+        assert_eq!(args.duration, Some(Duration::from_millis(50)));
+        let args = parse(&["--last", "--timelapse", "0.5", "--duration", "60"]);
+        assert_eq!(args.timelapse, Some(Duration::from_millis(500)));
+        assert_eq!(args.duration, Some(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,115 @@
+use image::{Rgba, RgbaImage};
+
+use crate::export::Rect;
+
+const OUTLINE: Rgba<u8> = Rgba([66, 245, 135, 255]);
+const OUTLINE_WIDTH: i32 = 3;
+
+/// A QR code found in a capture.
+#[derive(Clone, Debug)]
+pub struct Code {
+    pub text: String,
+    pub corners: [(i32, i32); 4], // In image coordinates, clockwise from the top left
+}
+
+impl Code {
+    /// Outlines the code, to show what was found.
+    pub fn draw(&self, image: &mut RgbaImage) {
+        for i in 0..4 {
+            let (from, to) = (self.corners[i], self.corners[(i + 1) % 4]);
+            line(image, from, to);
+        }
+    }
+}
+
+/// Finds and decodes every QR code inside `rect` of `image`.
+pub fn scan(image: &RgbaImage, rect: Rect) -> Vec<Code> {
+    let width = rect.right().min(image.width()).saturating_sub(rect.x);
+    let height = rect.bottom().min(image.height()).saturating_sub(rect.y);
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(width as usize, height as usize, |x, y| {
+            let Rgba([r, g, b, a]) = *image.get_pixel(rect.x + x as u32, rect.y + y as u32);
+            // Transparent pixels count as white, like the background of a code usually is
+            let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+            (255 - (255 - luma) * a as u32 / 255) as u8
+        });
+    prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| match grid.decode() {
+            Ok((_, text)) => Some(Code {
+                text,
+                corners: grid
+                    .bounds
+                    .map(|p| (p.x + rect.x as i32, p.y + rect.y as i32)),
+            }),
+            Err(err) => {
+                eprintln!("Could not decode QR code: {:?}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+fn line(image: &mut RgbaImage, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+    for step in 0..=steps {
+        let x = x0 + (x1 - x0) * step / steps;
+        let y = y0 + (y1 - y0) * step / steps;
+        for (dx, dy) in (0..OUTLINE_WIDTH).flat_map(|dx| (0..OUTLINE_WIDTH).map(move |dy| (dx, dy)))
+        {
+            let (px, py) = (x + dx - OUTLINE_WIDTH / 2, y + dy - OUTLINE_WIDTH / 2);
+            if px >= 0 && py >= 0 && (px as u32) < image.width() && (py as u32) < image.height() {
+                image.put_pixel(px as u32, py as u32, OUTLINE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn fixture() -> RgbaImage {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/qr.png");
+        image::open(path).unwrap().to_rgba8()
+    }
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn decodes_codes_in_the_rect() {
+        let image = fixture();
+        let codes = scan(&image, rect(0, 0, image.width(), image.height()));
+        assert_eq!(codes.len(), 1);
+        assert_eq!(codes[0].text, "https://example.com/cleave");
+        // The code starts after a quiet zone of 16 pixels
+        let (x, y) = codes[0].corners[0];
+        assert!(
+            (14..=18).contains(&x) && (14..=18).contains(&y),
+            "{:?}",
+            (x, y)
+        );
+        assert!(scan(&image, rect(0, 0, 40, 40)).is_empty());
+    }
+
+    #[test]
+    fn ignores_empty_rects() {
+        let image = fixture();
+        assert!(scan(&image, rect(50, 0, 0, image.height())).is_empty());
+        assert!(scan(&image, rect(0, 50, image.width(), 0)).is_empty());
+        assert!(scan(&image, rect(image.width(), 0, 10, 10)).is_empty());
+    }
+}
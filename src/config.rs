@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Context;
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::{
+    animation,
+    beautify::{self, Style},
+    export::Layout,
+};
+
+/// User configuration, read from `<config dir>/cleave/config.toml`.
+/// Every field is optional and falls back to its default.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub capture: CaptureConfig,
+    pub snap: SnapConfig,
+    pub export: ExportConfig,
+    pub history: HistoryConfig,
+    pub record: RecordConfig,
+    pub beautify: BeautifyConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub cursor: bool, // Whether the mouse pointer starts out drawn into captures
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct SnapConfig {
+    pub enabled: bool,
+    pub distance: f32, // In pixels, how close an edge has to be to snap to it
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distance: 8.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub layout: Layout,     // How multiple regions are combined into one image
+    pub trim_tolerance: u8, // How much a channel may differ from the background when trimming
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            layout: Layout::default(),
+            trim_tolerance: 8,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub max_entries: usize, // 0 keeps any number of captures
+    pub max_age_days: u32,  // 0 keeps captures forever
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 100,
+            max_age_days: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RecordConfig {
+    pub fps: u32,
+    pub format: animation::Format, // Used unless the output path has a known extension
+    pub max_seconds: u64,          // Recordings stop by themselves after this long
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            fps: 10,
+            format: animation::Format::Gif,
+            max_seconds: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct BeautifyConfig {
+    pub style: Option<String>, // Applied to every export unless changed in the overlay
+    pub styles: BTreeMap<String, Style>, // Replace the built-in styles if any are set
+}
+
+impl BeautifyConfig {
+    /// The styles to choose from, by name.
+    pub fn styles(&self) -> BTreeMap<String, Style> {
+        if self.styles.is_empty() {
+            beautify::builtin_styles()
+        } else {
+            self.styles.clone()
+        }
+    }
+
+    /// Styles `image` as the style with the given name, or leaves it alone if there is none.
+    pub fn apply(&self, image: RgbaImage, name: Option<&str>) -> anyhow::Result<RgbaImage> {
+        let Some(name) = name else {
+            return Ok(image);
+        };
+        let styles = self.styles();
+        let style = styles
+            .get(name)
+            .with_context(|| format!("There is no style named {}", name))?;
+        Ok(beautify::apply(&image, style))
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("cleave").join("config.toml"))
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path().filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read config at {}", path.display()))?;
+        Self::parse(&contents)
+            .with_context(|| format!("Could not parse config at {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        // Caught here rather than failing every export
+        if let Some(style) = &config.beautify.style {
+            if !config.beautify.styles().contains_key(style) {
+                anyhow::bail!("There is no style named {}", style);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Loads the config, warning and falling back to the defaults when it is invalid.
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|err| {
+            eprintln!("Using the default config: {:?}", err);
+            Self::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_styles() {
+        assert!(Config::parse("[beautify]\nstyle = \"ocean\"").is_ok());
+        assert!(Config::parse("[beautify]\nstyle = \"neon\"").is_err());
+        let custom = "[beautify]\nstyle = \"docs\"\n[beautify.styles.docs]\n";
+        assert!(Config::parse(custom).is_ok());
+        assert!(Config::parse(&custom.replace("\"docs\"\n", "\"ocean\"\n")).is_err());
+    }
+}
@@ -1,16 +1,44 @@
+use std::time::SystemTime;
+
 use anyhow::Context;
-use arboard::ImageData;
-use glam::{DVec2, Vec2};
-use image::{GenericImageView, ImageBuffer, Rgba};
+use glam::{DVec2, UVec4, Vec2, Vec4};
+use image::{ImageBuffer, Rgba, RgbaImage};
 // use pixels::{Pixels, SurfaceTexture};
 use winit::{
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     window::{Icon, Window, WindowAttributes},
 };
 
 // use crate::{graphics_bundle::GraphicsBundle, graphics_impl::Graphics};
 use cleave_graphics::prelude::*;
 
+use crate::{
+    archive,
+    capture::{Capture, Cursor, Source, WindowInfo},
+    codes::{self, Code},
+    config::{Config, RecordConfig},
+    export::{self, Rect},
+    label,
+    metadata::Metadata,
+    presets::{self, Picker, Preset},
+    selection::{Drag, Selection, Shape, UserSelection},
+    snap::SnapTargets,
+    state::LastRegion,
+    trim,
+    undo::History,
+};
+
+// Must match the length of `regions` in the shader
+pub const MAX_REGIONS: usize = 16;
+// Must match twice the length of `lasso_points` in the shader, two points are packed per vec4
+const MAX_LASSO_POINTS: usize = 256;
+// Lassos are thinned out to at most this many points for drawing
+const MAX_POINTS_PER_LASSO: usize = 64;
+// How long a notice stays on the overlay
+const NOTICE_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
 pub enum MoveMode {
     Move,          // Move the selection
     InverseResize, // Make the selection smaller
@@ -25,7 +53,7 @@ pub enum Direction {
 }
 
 #[repr(C)]
-#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, Default, Debug)]
+#[derive(bytemuck::Pod, bytemuck::Zeroable, Copy, Clone, Debug)]
 pub struct SelectionUniforms {
     screen_size: Vec2,
     drag_start: Vec2,
@@ -34,6 +62,20 @@ pub struct SelectionUniforms {
     selection_end: Vec2,
     time: f32,
     is_dragging: u32, // 0 = None, 1 = Dragging, 2 = Selected, 3 = Both
+    region_count: u32,
+    _padding: [u32; 3],
+    regions: [Vec4; MAX_REGIONS], // Additional selected regions as (start, end)
+    // Shapes as (id, first lasso point, lasso point count, unused)
+    drag_shape: UVec4,
+    selection_shape: UVec4,
+    region_shapes: [UVec4; MAX_REGIONS],
+    lasso_points: [Vec4; MAX_LASSO_POINTS / 2],
+}
+
+impl Default for SelectionUniforms {
+    fn default() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
 }
 
 impl std::fmt::Display for SelectionUniforms {
@@ -43,183 +85,525 @@ impl std::fmt::Display for SelectionUniforms {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct Drag {
-    start: Vec2,
-    end: Option<Vec2>,
-}
-
-#[derive(Clone, Copy, Debug)]
-pub struct Selection {
-    start: Vec2,
-    end: Vec2,
-}
-
-pub struct UserSelection {
-    drag: Option<Drag>,
-    selection: Option<Selection>,
+/// Packs the lasso points of every shape drawn in a frame into the uniforms.
+struct LassoPacker<'a> {
+    points: &'a mut [Vec4; MAX_LASSO_POINTS / 2],
+    len: usize,
 }
 
-impl UserSelection {
-    fn new() -> Self {
-        Self {
-            drag: None,
-            selection: None,
+impl LassoPacker<'_> {
+    fn pack(&mut self, shape: &Shape) -> UVec4 {
+        let Shape::Lasso(points) = shape else {
+            return UVec4::new(shape.id(), 0, 0, 0);
+        };
+        let step = points.len().div_ceil(MAX_POINTS_PER_LASSO).max(1);
+        let count = points.len().div_ceil(step);
+        if points.len() < 2 || self.len + count > MAX_LASSO_POINTS {
+            return UVec4::new(Shape::Rect.id(), 0, 0, 0); // Out of space, draw the bounds
         }
+        let first = self.len;
+        for point in points.iter().step_by(step) {
+            let packed = &mut self.points[self.len / 2];
+            if self.len % 2 == 1 {
+                (packed.z, packed.w) = (point.x, point.y);
+            } else {
+                (packed.x, packed.y) = (point.x, point.y);
+            }
+            self.len += 1;
+        }
+        UVec4::new(shape.id(), first as u32, count as u32, 0)
     }
-
-    fn sel_coords(&self) -> Option<((u32, u32), (u32, u32))> {
-        let selection = self.selection.as_ref()?;
-        let (start_x, start_y) = (selection.start.x, selection.start.y);
-        let (end_x, end_y) = (selection.end.x, selection.end.y);
-
-        let (min_x, max_x) = (start_x.min(end_x).ceil(), start_x.max(end_x).floor());
-        let (min_y, max_y) = (start_y.min(end_y).ceil(), start_y.max(end_y).floor());
-        Some(((min_x as u32, min_y as u32), (max_x as u32, max_y as u32)))
-    }
-
-    fn sel_dimensions(&self) -> Option<(f32, f32)> {
-        let selection = self.selection.as_ref()?;
-        let width = (selection.end.x - selection.start.x).abs();
-        let height = (selection.end.y - selection.start.y).abs();
-        Some((width, height))
-    }
-
-    // fn get_
 }
 
 pub struct AppContext {
     size: PhysicalSize<u32>,
     mouse_position: DVec2,
     selection: UserSelection,
+    history: History<UserSelection>,
     // current_drag: Option<Drag>,
     // selection: Option<Selection>,
-    image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    image: ImageBuffer<Rgba<u8>, Vec<u8>>, // What is shown and exported, may include the cursor
+    screen: RgbaImage,                     // The capture as it came from the source
+    cursor: Option<Cursor>,
+    show_cursor: bool,
+    codes: Vec<Code>, // Highlighted on screen, but never part of the exported image
+    notice: Option<(String, std::time::Instant)>, // Shown on the overlay until it expires
+    picker: Option<Picker>, // Presets listed on the overlay
+    style: Option<String>, // Beautify style for exports
+    scale_factor: f32, // Of the captured monitor
+    captured_at: SystemTime,
     // pixels: Pixels<'static>,
     total_time: f32,
     last_frame: std::time::Instant,
     graphics: Graphics<Window>,
     bundle: GraphicsBundle<SelectionUniforms>,
     mode: MoveMode,
+    shape: Shape, // Shape of the next drag
+    config: Config,
+    snap_targets: SnapTargets,
+    windows: Vec<WindowInfo>, // For presets relative to a window
+    snapping: bool,
+    visible: bool,
+    monitor: String, // Name of the captured monitor
+    monitor_id: u32,
+    source: Source,
 }
 
 impl AppContext {
-    pub fn start_drag(&mut self) {
+    pub fn start_drag(&mut self, additive: bool) {
         if let Some(drag) = self.selection.drag.as_mut() {
             if drag.start != Vec2::ZERO {
                 return;
             }
         };
+        let start = match self.shape {
+            Shape::Lasso(_) => self.mouse_position.as_vec2(),
+            _ => self.snap(self.mouse_position.as_vec2()),
+        };
+        let shape = match self.shape {
+            Shape::Lasso(_) => Shape::Lasso(vec![start]),
+            ref shape => shape.clone(),
+        };
         self.selection.drag = Some(Drag {
-            start: self.mouse_position.as_vec2(),
-            end: Some(self.mouse_position.as_vec2()),
+            start,
+            end: Some(start),
+            additive,
+            shape,
         });
     }
 
     pub fn end_drag(&mut self) {
-        self.selection.selection = None;
-        if let Some(drag) = self.selection.drag.take() {
-            let end_pos = drag.end.unwrap_or(drag.start); // Use end if set, otherwise use start
-            self.selection.selection = Some(Selection {
-                start: drag.start,
-                end: end_pos,
-            });
+        let Some(drag) = self.selection.drag.take() else {
+            return;
+        };
+        if drag.additive
+            && self.selection.selection.is_some()
+            && self.selection.regions.len() == MAX_REGIONS
+        {
+            self.notify(format!(
+                "At most {} regions can be selected",
+                MAX_REGIONS + 1
+            ));
+            return;
         }
+        self.history.record(self.selection.committed());
+        let previous = self.selection.selection.take();
+        if !drag.additive {
+            self.selection.regions.clear();
+        } else if let Some(previous) = previous {
+            self.selection.regions.push(previous);
+        }
+        self.selection.selection = drag.finish();
     }
 
     pub fn cancel_drag(&mut self) {
+        if self.selection.selection.is_some() || !self.selection.regions.is_empty() {
+            self.history.record(self.selection.committed());
+        }
         self.selection.drag = None;
         self.selection.selection = None;
+        self.selection.regions.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(previous) = self.history.undo(self.selection.committed()) {
+            self.selection = previous;
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(next) = self.history.redo(self.selection.committed()) {
+            self.selection = next;
+        }
+    }
+
+    pub fn selected_image(&self) -> Option<RgbaImage> {
+        export::compose(
+            &self.image,
+            &self.selection.regions(),
+            self.config.export.layout,
+        )
     }
 
-    fn get_selection_data(&self) -> Option<Vec<u8>> {
-        let ((min_x, min_y), (max_x, max_y)) = self.selection.sel_coords()?;
-        let img = self
-            .image
-            .view(min_x, min_y, max_x.abs_diff(min_x), max_y.abs_diff(min_y));
-        let image_data = img.to_image().to_vec();
-        Some(image_data)
+    /// Switches to the next beautify style, after the last one exports go back to plain.
+    pub fn cycle_style(&mut self) -> Option<&str> {
+        let styles = self.config.beautify.styles();
+        let mut names = styles.keys();
+        self.style = match &self.style {
+            None => names.next().cloned(),
+            Some(current) => names.skip_while(|n| *n != current).nth(1).cloned(),
+        };
+        self.style.as_deref()
     }
 
-    pub fn save_selection_to_clipboard(&self) {
-        let (width, height) = self.selection.sel_dimensions().unwrap();
+    /// Applies the current beautify style to an exported image.
+    pub fn styled(&self, image: RgbaImage) -> anyhow::Result<RgbaImage> {
+        self.config.beautify.apply(image, self.style.as_deref())
+    }
 
-        let width = width.floor() as usize;
-        let height = height.floor() as usize;
+    /// Where the selection came from, to embed into saved images.
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(
+            &self.monitor,
+            Rect::bounds(&self.selected_rects()),
+            self.scale_factor,
+            self.captured_at,
+        )
+    }
 
-        let image_data = self.get_selection_data().unwrap();
+    /// Keeps a delivered selection in the capture history, and remembers it to repeat it later.
+    pub fn record_selection(&self, image: &RgbaImage) -> anyhow::Result<()> {
+        let rect = Rect::bounds(&self.selected_rects());
+        // Only screen regions can be captured again, files and the clipboard all look like
+        // monitor 0
+        let remembered = match rect {
+            Some(rect) if self.source.is_screen() => LastRegion {
+                monitor: self.monitor.clone(),
+                monitor_id: self.monitor_id,
+                rect,
+            }
+            .save(),
+            _ => Ok(()),
+        };
+        archive::record(&self.config.history, image, &self.monitor, rect)?;
+        remembered
+    }
 
-        let mut clipboard = arboard::Clipboard::new().unwrap();
-        if width * height != image_data.len() / 4 {
-            eprintln!(
-                "Invalid selection size {:?} (w h p)",
-                (width, height, image_data.len() / 4)
+    /// Selects the region that was captured last, to adjust it or capture it again.
+    pub fn restore_last_selection(&mut self) -> anyhow::Result<()> {
+        if !self.source.is_screen() {
+            anyhow::bail!("The last region can only be selected on the screen");
+        }
+        let last = LastRegion::load()?.context("No region has been captured yet")?;
+        if last.monitor_id != self.monitor_id {
+            anyhow::bail!(
+                "The last region was captured on {}, not {}",
+                last.monitor,
+                self.monitor
             );
-            return;
         }
-        let image_data = ImageData {
-            width,
-            height,
-            bytes: std::borrow::Cow::Owned(image_data),
+        self.set_selection(last.rect)
+    }
+
+    /// Saves the bounds of the selection as a named preset, optionally relative to the window
+    /// it is on.
+    pub fn save_preset(&self, name: &str, relative: bool) -> anyhow::Result<()> {
+        let rect = Rect::bounds(&self.selected_rects()).context("Nothing selected")?;
+        let preset = if relative {
+            Preset::relative_to_window(rect, &self.windows)
+        } else {
+            Preset {
+                rect,
+                monitor_id: None,
+                window: None,
+            }
         };
-        let _ = clipboard.set_image(image_data);
+        presets::save(
+            name,
+            Preset {
+                monitor_id: Some(self.monitor_id),
+                ..preset
+            },
+        )
+    }
+
+    /// Lists the saved presets on the overlay to choose one from.
+    pub fn open_preset_picker(&mut self) -> anyhow::Result<()> {
+        self.picker = Some(Picker::new(presets::load()?)?);
+        self.update_image();
+        Ok(())
+    }
+
+    pub fn close_preset_picker(&mut self) {
+        self.picker = None;
+        self.update_image();
+    }
+
+    pub fn is_picking_preset(&self) -> bool {
+        self.picker.is_some()
     }
 
-    pub fn new(event_loop: &winit::event_loop::ActiveEventLoop) -> anyhow::Result<Self> {
-        let monitor = xcap::Monitor::all()?
-            .into_iter()
-            .find(|m| m.is_primary())
-            .with_context(|| "Could not get primary monitor")?;
-        let img = monitor.capture_image()?;
-        let size = PhysicalSize::new(monitor.width(), monitor.height());
+    pub fn move_preset_highlight(&mut self, delta: isize) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.move_by(delta);
+            self.update_image();
+        }
+    }
+
+    /// Selects the preset at `index` in the picker, or the highlighted one, and closes it.
+    pub fn choose_preset(&mut self, index: Option<usize>) -> anyhow::Result<()> {
+        let (name, preset) = self
+            .picker
+            .as_ref()
+            .and_then(|picker| picker.get(index))
+            .cloned()
+            .context("There is no such preset")?;
+        self.close_preset_picker();
+        if !preset.on_monitor(self.monitor_id) {
+            anyhow::bail!("The preset {} is on another monitor", name);
+        }
+        let rect = preset.resolve(&self.windows)?;
+        self.set_selection(rect)
+    }
+
+    /// Shrinks the active selection to cut off margins of a single colour.
+    pub fn trim_selection(&mut self) -> anyhow::Result<()> {
+        let selection = self
+            .selection
+            .selection
+            .as_ref()
+            .context("Nothing selected")?;
+        // Moving the edges of a lasso or an ellipse would stretch it over different content
+        if selection.shape != Shape::Rect {
+            anyhow::bail!("Only rectangular selections can be trimmed");
+        }
+        let rect = selection.rect();
+        let trimmed = trim::trim(&self.image, rect, self.config.export.trim_tolerance)
+            .context("The selection is all one colour")?;
+        if trimmed == rect {
+            return Ok(());
+        }
+        self.history.record(self.selection.committed());
+        if let Some(selection) = self.selection.selection.as_mut() {
+            selection.set_bounds(
+                Vec2::new(trimmed.x as f32, trimmed.y as f32),
+                Vec2::new(trimmed.right() as f32, trimmed.bottom() as f32),
+            );
+        }
+        Ok(())
+    }
+
+    /// The rectangles of every selected region, ending with the active selection.
+    pub fn selected_rects(&self) -> Vec<Rect> {
+        self.selection.regions().iter().map(|r| r.rect).collect()
+    }
+
+    pub fn monitor(&self) -> &str {
+        &self.monitor
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub fn monitor_id(&self) -> u32 {
+        self.monitor_id
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// Replaces all selected regions with a single rectangle, cut to the part inside the image.
+    pub fn set_selection(&mut self, rect: Rect) -> anyhow::Result<()> {
+        let image = Rect {
+            x: 0,
+            y: 0,
+            width: self.size.width,
+            height: self.size.height,
+        };
+        let rect = rect
+            .intersect(&image)
+            .context("The region is outside the captured image")?;
+        self.history.record(self.selection.committed());
+        let (x, y) = (rect.x as f32, rect.y as f32);
+        self.selection = UserSelection::new();
+        self.selection.selection = Some(Selection {
+            start: Vec2::new(x, y),
+            end: Vec2::new(x + rect.width as f32, y + rect.height as f32),
+            shape: Shape::Rect,
+        });
+        Ok(())
+    }
+
+    /// Captures the source and sets up the overlay, which is only shown if `visible` is set.
+    pub fn new(
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        visible: bool,
+        source: Source,
+    ) -> anyhow::Result<Self> {
+        let Capture {
+            monitor,
+            monitor_id,
+            image: img,
+            size,
+            snap_targets,
+            cursor,
+            windows,
+            scale_factor,
+            captured_at,
+        } = Capture::new(&source)?;
+        let config = Config::load_or_default();
 
         let icon_bytes = include_bytes!("../icon.png");
         let rgba = image::load_from_memory(icon_bytes)?.to_rgba8();
         let (width, height) = rgba.dimensions();
         let rgba = rgba.into_raw();
 
-        let window = event_loop.create_window(
-            WindowAttributes::default()
-                .with_inner_size(size)
-                .with_title("Cleave")
-                .with_resizable(false)
-                .with_decorations(false)
-                .with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)))
-                .with_visible(false)
-                .with_window_icon(Some(Icon::from_rgba(rgba, width, height)?)),
-        )?;
-
-        let graphics = Graphics::new(window, size.width, size.height);
+        let attributes = WindowAttributes::default()
+            .with_title("Cleave")
+            .with_resizable(false)
+            .with_visible(false)
+            .with_window_icon(Some(Icon::from_rgba(rgba, width, height)?));
+        let (attributes, window_size) = if source.is_screen() {
+            (
+                attributes
+                    .with_inner_size(size)
+                    .with_decorations(false)
+                    .with_fullscreen(Some(winit::window::Fullscreen::Borderless(None))),
+                size,
+            )
+        } else {
+            // Shrink images that don't fit on the screen, selections stay in image pixels
+            let screen = event_loop
+                .primary_monitor()
+                .map(|m| m.size())
+                .unwrap_or(size);
+            let scale = (0.9 * screen.width as f32 / size.width as f32)
+                .min(0.9 * screen.height as f32 / size.height as f32)
+                .min(1.0);
+            let window_size = PhysicalSize::new(
+                (size.width as f32 * scale).max(1.0) as u32,
+                (size.height as f32 * scale).max(1.0) as u32,
+            );
+            (attributes.with_inner_size(window_size), window_size)
+        };
+        let window = event_loop.create_window(attributes)?;
+
+        let graphics = Graphics::new(window, window_size.width, window_size.height);
         let graphics = pollster::block_on(graphics)?;
 
+        // Nothing else is drawn over the capture yet, so this is all the texture needs
+        let show_cursor = config.capture.cursor;
+        let image = with_cursor(&img, cursor.as_ref().filter(|_| show_cursor));
         let bundle = GraphicsBundle::new(
-            img.clone().into(),
+            image.clone().into(),
             &graphics.device,
             &graphics.queue,
             wgpu::PrimitiveTopology::TriangleStrip,
             graphics.config.format,
         );
 
-        graphics.window.set_visible(true);
-        let _ = graphics
-            .window
-            .set_cursor_grab(winit::window::CursorGrabMode::Confined);
-
         // let surface_texture = SurfaceTexture::new(size.width, size.height, window.clone());
         // let pixels = Pixels::new(size.width, size.height, surface_texture)?;
 
-        Ok(Self {
+        let mut context = Self {
             size,
-            image: img,
+            image,
+            screen: img,
+            cursor,
+            show_cursor,
+            codes: Vec::new(),
+            notice: None,
+            picker: None,
+            style: config.beautify.style.clone(),
+            scale_factor,
+            captured_at,
             bundle,
             total_time: 0.0,
             last_frame: std::time::Instant::now(),
             selection: UserSelection::new(),
+            history: History::new(),
             // window,
             graphics,
             mouse_position: DVec2::new(0.0, 0.0),
             mode: MoveMode::Resize,
-        })
+            shape: Shape::Rect,
+            config,
+            snap_targets,
+            windows,
+            snapping: true,
+            visible: false,
+            monitor,
+            monitor_id,
+            source,
+        };
+        if visible {
+            context.show_window();
+        }
+        Ok(context)
+    }
+
+    /// Takes a new capture for the existing overlay and shows it with a fresh selection.
+    pub fn recapture(&mut self) -> anyhow::Result<()> {
+        let capture = Capture::new(&self.source)?;
+        if capture.size != self.size {
+            self.size = capture.size;
+            self.graphics.resize(self.size.width, self.size.height);
+        }
+        self.screen = capture.image;
+        self.cursor = capture.cursor;
+        self.show_cursor = self.config.capture.cursor;
+        self.codes.clear();
+        self.notice = None;
+        self.picker = None;
+        self.style = self.config.beautify.style.clone();
+        self.update_image();
+        self.monitor = capture.monitor;
+        self.monitor_id = capture.monitor_id;
+        self.scale_factor = capture.scale_factor;
+        self.captured_at = capture.captured_at;
+        self.snap_targets = capture.snap_targets;
+        self.windows = capture.windows;
+        self.selection = UserSelection::new();
+        self.history = History::new();
+        self.mode = MoveMode::Resize;
+        self.shape = Shape::Rect;
+        self.snapping = true;
+        self.show_window();
+        Ok(())
+    }
+
+    /// Draws or removes the mouse pointer, if the source knows where it was.
+    pub fn toggle_cursor(&mut self) {
+        if self.cursor.is_some() {
+            self.show_cursor = !self.show_cursor;
+            self.update_image();
+        }
+    }
+
+    /// Decodes the QR codes in the selection, or on the whole screen if nothing is selected,
+    /// and highlights them.
+    pub fn scan_codes(&mut self) -> Vec<String> {
+        let rect = Rect::bounds(&self.selected_rects()).unwrap_or(Rect {
+            x: 0,
+            y: 0,
+            width: self.image.width(),
+            height: self.image.height(),
+        });
+        self.codes = codes::scan(&self.image, rect);
+        self.update_image();
+        self.codes.iter().map(|code| code.text.clone()).collect()
+    }
+
+    /// Shows a short message on the overlay.
+    pub fn notify(&mut self, text: String) {
+        self.notice = Some((text, std::time::Instant::now()));
+        self.update_image();
+    }
+
+    fn update_image(&mut self) {
+        let image = with_cursor(
+            &self.screen,
+            self.cursor.as_ref().filter(|_| self.show_cursor),
+        );
+        let mut texture = image.clone();
+        for code in &self.codes {
+            code.draw(&mut texture);
+        }
+        let mut lines = match &self.picker {
+            Some(picker) => picker.lines(self.monitor_id),
+            None => Vec::new(),
+        };
+        lines.extend(self.notice.iter().map(|(text, _)| text.clone()));
+        label::draw(&mut texture, &lines, self.label_scale());
+        self.bundle
+            .set_texture(texture.into(), &self.graphics.device, &self.graphics.queue);
+        self.image = image;
+    }
+
+    fn label_scale(&self) -> u32 {
+        (self.scale_factor * 2.0).round().max(1.0) as u32
     }
 
     pub fn handle_move(&mut self, dir: Direction) -> Option<()> {
@@ -230,32 +614,47 @@ impl AppContext {
             Direction::Right => (1.0, 0.0),
         };
 
+        self.selection.selection.as_ref()?;
+        self.history.record(self.selection.committed());
         let selection = self.selection.selection.as_mut()?;
 
+        let (mut start, mut end) = (selection.start, selection.end);
         match self.mode {
             MoveMode::Move => {
-                selection.start.x = (selection.start.x + dx).clamp(0.0, self.size.width as f32);
-                selection.start.y = (selection.start.y + dy).clamp(0.0, self.size.height as f32);
-                selection.end.x = (selection.end.x + dx).clamp(0.0, self.size.width as f32);
-                selection.end.y = (selection.end.y + dy).clamp(0.0, self.size.height as f32);
+                start.x = (start.x + dx).clamp(0.0, self.size.width as f32);
+                start.y = (start.y + dy).clamp(0.0, self.size.height as f32);
+                end.x = (end.x + dx).clamp(0.0, self.size.width as f32);
+                end.y = (end.y + dy).clamp(0.0, self.size.height as f32);
             }
             MoveMode::Resize => {
-                selection.end.x = (selection.end.x + dx).clamp(0.0, self.size.width as f32);
-                selection.end.y = (selection.end.y + dy).clamp(0.0, self.size.height as f32);
+                end.x = (end.x + dx).clamp(0.0, self.size.width as f32);
+                end.y = (end.y + dy).clamp(0.0, self.size.height as f32);
             }
             MoveMode::InverseResize => {
-                selection.start.x = (selection.start.x + dx).clamp(0.0, self.size.width as f32);
-                selection.start.y = (selection.start.y + dy).clamp(0.0, self.size.height as f32);
+                start.x = (start.x + dx).clamp(0.0, self.size.width as f32);
+                start.y = (start.y + dy).clamp(0.0, self.size.height as f32);
             }
         }
+        selection.set_bounds(start, end);
 
         Some(())
     }
 
     pub fn draw(&mut self) {
+        if !self.visible {
+            return;
+        }
         let time = self.last_frame.elapsed().as_secs_f32();
         self.total_time += time;
         self.last_frame = std::time::Instant::now();
+        if self
+            .notice
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() > NOTICE_DURATION)
+        {
+            self.notice = None;
+            self.update_image();
+        }
 
         self.update_uniforms();
         self.bundle.update_buffer(&self.graphics.queue);
@@ -277,8 +676,8 @@ impl AppContext {
         self.bundle.uniforms.screen_size.x = self.size.width as f32;
         self.bundle.uniforms.screen_size.y = self.size.height as f32;
 
-        let drag = self.selection.drag;
-        let selection = self.selection.selection;
+        let drag = self.selection.drag.as_ref();
+        let selection = self.selection.selection.as_ref();
         self.bundle.uniforms.is_dragging = match (drag, selection) {
             (Some(d), Some(s)) if d.start != Vec2::ZERO || s.start != Vec2::ZERO => 3,
             (Some(d), None) if d.start != Vec2::ZERO => 1,
@@ -301,6 +700,38 @@ impl AppContext {
             self.bundle.uniforms.selection_start = Vec2::ZERO;
             self.bundle.uniforms.selection_end = Vec2::ZERO;
         };
+
+        let regions = &self.selection.regions;
+        let uniforms = &mut self.bundle.uniforms;
+        uniforms.region_count = regions.len() as u32;
+        for (uniform, region) in uniforms.regions.iter_mut().zip(regions) {
+            *uniform = Vec4::new(region.start.x, region.start.y, region.end.x, region.end.y);
+        }
+
+        let mut lasso = LassoPacker {
+            points: &mut uniforms.lasso_points,
+            len: 0,
+        };
+        uniforms.drag_shape = drag.map(|d| lasso.pack(&d.shape)).unwrap_or_default();
+        uniforms.selection_shape = selection.map(|s| lasso.pack(&s.shape)).unwrap_or_default();
+        for (uniform, region) in uniforms.region_shapes.iter_mut().zip(regions) {
+            *uniform = lasso.pack(&region.shape);
+        }
+    }
+
+    /// Where a point in the image is on the screen.
+    pub fn screen_position(&self, x: u32, y: u32) -> PhysicalPosition<i32> {
+        let origin = self.graphics.window.inner_position().unwrap_or_default();
+        let scale = self.graphics.size.as_dvec2()
+            / DVec2::new(self.size.width as f64, self.size.height as f64);
+        PhysicalPosition::new(
+            origin.x + (x as f64 * scale.x) as i32,
+            origin.y + (y as f64 * scale.y) as i32,
+        )
+    }
+
+    pub fn record_config(&self) -> &RecordConfig {
+        &self.config.record
     }
 
     pub fn window_id(&self) -> winit::window::WindowId {
@@ -311,18 +742,76 @@ impl AppContext {
         self.graphics.window.set_minimized(true);
     }
 
-    pub fn hide_window(&self) {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn hide_window(&mut self) {
+        self.visible = false;
         self.graphics.set_visible(false);
+        let _ = self
+            .graphics
+            .set_cursor_grab(winit::window::CursorGrabMode::None);
+    }
+
+    fn show_window(&mut self) {
+        self.visible = true;
+        self.last_frame = std::time::Instant::now();
+        self.graphics.set_visible(true);
+        if self.source.is_screen() {
+            let _ = self
+                .graphics
+                .set_cursor_grab(winit::window::CursorGrabMode::Confined);
+        }
+        self.graphics.request_redraw();
     }
 
     pub fn set_mode(&mut self, mode: MoveMode) {
         self.mode = mode
     }
 
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+    pub fn set_snapping(&mut self, snapping: bool) {
+        self.snapping = snapping;
+    }
+
+    fn snap(&self, pos: Vec2) -> Vec2 {
+        if !self.snapping || !self.config.snap.enabled {
+            return pos;
+        }
+        self.snap_targets.snap(pos, self.config.snap.distance)
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.graphics.resize(size.width, size.height);
+    }
+
     pub fn update_mouse_position(&mut self, x: f64, y: f64) {
-        self.mouse_position = DVec2::new(x, y);
+        // The window can be smaller than the image, selections are in image pixels
+        let scale = DVec2::new(self.size.width as f64, self.size.height as f64)
+            / self.graphics.size.as_dvec2().max(DVec2::ONE);
+        self.mouse_position = DVec2::new(x, y) * scale;
+        let position = self.mouse_position.as_vec2();
+        let end = self.snap(position);
         if let Some(drag) = self.selection.drag.as_mut() {
-            drag.end = Some(self.mouse_position.as_vec2());
+            if let Shape::Lasso(points) = &mut drag.shape {
+                points.push(position);
+                drag.end = Some(position);
+            } else {
+                drag.end = Some(end);
+            }
         }
     }
 }
+
+/// The capture as it is shown and exported, with the pointer drawn in if given.
+fn with_cursor(screen: &RgbaImage, cursor: Option<&Cursor>) -> RgbaImage {
+    let mut image = screen.clone();
+    if let Some(cursor) = cursor {
+        cursor.draw(&mut image);
+    }
+    image
+}
@@ -0,0 +1,142 @@
+use std::{
+    fs::DirBuilder,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::Context;
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    ipc::{Command, Response},
+    AppEvent,
+};
+
+/// The socket in the user's runtime directory, or without one in a directory under the temp
+/// directory that only the user can access, so other users can't listen in or pose as the daemon.
+pub fn socket_path() -> anyhow::Result<PathBuf> {
+    let dir = match dirs::runtime_dir() {
+        Some(dir) => dir,
+        None => private_dir(&std::env::temp_dir())?,
+    };
+    Ok(dir.join("cleave.sock"))
+}
+
+/// Creates `cleave-<uid>` in `parent` if needed, and checks that only the current user can
+/// access it.
+fn private_dir(parent: &Path) -> anyhow::Result<PathBuf> {
+    let uid = unsafe { libc::getuid() };
+    let dir = parent.join(format!("cleave-{}", uid));
+    if let Err(err) = DirBuilder::new().mode(0o700).create(&dir) {
+        if err.kind() != std::io::ErrorKind::AlreadyExists {
+            return Err(err).with_context(|| format!("Could not create {}", dir.display()));
+        }
+    }
+    let metadata = std::fs::symlink_metadata(&dir)
+        .with_context(|| format!("Could not access {}", dir.display()))?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        anyhow::bail!(
+            "{} is not a private directory owned by the current user",
+            dir.display()
+        );
+    }
+    Ok(dir)
+}
+
+/// Sends a JSON command to a running daemon and returns its JSON reply,
+/// `None` if no daemon is listening.
+pub fn send(command: &str) -> anyhow::Result<Option<String>> {
+    let Ok(mut stream) = UnixStream::connect(socket_path()?) else {
+        return Ok(None);
+    };
+    writeln!(stream, "{}", command.trim())?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(Some(reply.trim_end().to_string()))
+}
+
+/// Asks a running daemon to start a capture, returns `false` if no daemon is listening.
+pub fn request_capture() -> anyhow::Result<bool> {
+    let command = serde_json::to_string(&Command::Capture)?;
+    Ok(send(&command)?.is_some())
+}
+
+/// Listens for commands on a background thread and forwards them to the event loop.
+pub fn listen(proxy: EventLoopProxy<AppEvent>) -> anyhow::Result<()> {
+    let path = socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        anyhow::bail!("A daemon is already listening on {}", path.display());
+    }
+    // Left behind by a daemon that didn't shut down cleanly
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Could not listen on {}", path.display()))?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let proxy = proxy.clone();
+            std::thread::spawn(move || serve(stream, proxy));
+        }
+    });
+    Ok(())
+}
+
+/// Answers every command on a connection until the client hangs up.
+fn serve(stream: UnixStream, proxy: EventLoopProxy<AppEvent>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Command>(&line) {
+            Ok(command) => {
+                let (reply, response) = mpsc::channel();
+                if proxy.send_event(AppEvent::Command(command, reply)).is_err() {
+                    return; // Event loop is gone
+                }
+                response
+                    .recv()
+                    .unwrap_or_else(|_| Response::error("No response"))
+            }
+            Err(err) => Response::error(format!("Invalid command: {}", err)),
+        };
+        let Ok(json) = serde_json::to_string(&response) else {
+            return;
+        };
+        if writeln!(writer, "{}", json).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn private_dir_is_only_accessible_by_the_user() {
+        let parent = std::env::temp_dir().join(format!("cleave-test-{}", std::process::id()));
+        std::fs::create_dir_all(&parent).unwrap();
+
+        let dir = private_dir(&parent).unwrap();
+        let metadata = std::fs::metadata(&dir).unwrap();
+        assert_eq!(metadata.mode() & 0o777, 0o700);
+        assert_eq!(private_dir(&parent).unwrap(), dir);
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(private_dir(&parent).is_err());
+        std::fs::remove_dir_all(&parent).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use glam::Vec2;
+use image::{imageops, DynamicImage, ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::{self, Metadata};
+
+/// A rectangle of whole pixels in the captured image.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// The smallest rectangle containing all of `rects`.
+    pub fn bounds(rects: &[Rect]) -> Option<Rect> {
+        let x = rects.iter().map(|r| r.x).min()?;
+        let y = rects.iter().map(|r| r.y).min()?;
+        let right = rects.iter().map(Rect::right).max()?;
+        let bottom = rects.iter().map(Rect::bottom).max()?;
+        Some(Rect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+
+    // Saturating, as rects also come from the control socket and hand-edited presets
+    pub fn right(&self) -> u32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y.saturating_add(self.height)
+    }
+
+    /// The part of the rectangle inside `other`, if they overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (right > x && bottom > y).then(|| Rect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+}
+
+/// Which pixels of a region's bounding rectangle are kept, everything else becomes transparent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mask {
+    None,
+    Ellipse,
+    Polygon(Vec<Vec2>), // Relative to the top left of the region
+}
+
+impl Mask {
+    fn contains(&self, point: Vec2, size: Vec2) -> bool {
+        match self {
+            Mask::None => true,
+            Mask::Ellipse => {
+                let radius = size / 2.0;
+                let d = (point - radius) / radius;
+                d.length_squared() <= 1.0
+            }
+            Mask::Polygon(points) => {
+                // Even-odd rule
+                let mut inside = false;
+                let mut prev = points[points.len() - 1];
+                for &p in points {
+                    if (p.y > point.y) != (prev.y > point.y)
+                        && point.x < (prev.x - p.x) * (point.y - p.y) / (prev.y - p.y) + p.x
+                    {
+                        inside = !inside;
+                    }
+                    prev = p;
+                }
+                inside
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub rect: Rect,
+    pub mask: Mask,
+}
+
+/// How several selected regions are arranged in the output image.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Keep the regions where they were on screen, with transparent gaps between them
+    #[default]
+    Original,
+    /// Place the regions side by side, left to right in the order they were selected
+    Packed,
+}
+
+/// Crops every region out of `image` and composites them into one image.
+pub fn compose(image: &RgbaImage, regions: &[Region], layout: Layout) -> Option<RgbaImage> {
+    let regions: Vec<&Region> = regions
+        .iter()
+        .filter(|r| r.rect.width > 0 && r.rect.height > 0)
+        .collect();
+    if let [region] = regions.as_slice() {
+        return Some(crop(image, region));
+    }
+
+    let placements: Vec<(&Region, u32, u32)> = match layout {
+        Layout::Original => {
+            let min_x = regions.iter().map(|r| r.rect.x).min()?;
+            let min_y = regions.iter().map(|r| r.rect.y).min()?;
+            regions
+                .iter()
+                .map(|r| (*r, r.rect.x - min_x, r.rect.y - min_y))
+                .collect()
+        }
+        Layout::Packed => {
+            let mut x = 0;
+            regions
+                .iter()
+                .map(|r| {
+                    let placement = (*r, x, 0);
+                    x += r.rect.width;
+                    placement
+                })
+                .collect()
+        }
+    };
+
+    let width = placements.iter().map(|(r, x, _)| x + r.rect.width).max()?;
+    let height = placements.iter().map(|(r, _, y)| y + r.rect.height).max()?;
+    let mut output = RgbaImage::new(width, height);
+    for (region, x, y) in placements {
+        imageops::overlay(&mut output, &crop(image, region), x as i64, y as i64);
+    }
+    Some(output)
+}
+
+fn crop(image: &RgbaImage, region: &Region) -> RgbaImage {
+    let rect = region.rect;
+    let width = rect.right().min(image.width()).saturating_sub(rect.x);
+    let height = rect.bottom().min(image.height()).saturating_sub(rect.y);
+    let mut output = imageops::crop_imm(image, rect.x, rect.y, width, height).to_image();
+    if region.mask != Mask::None {
+        let size = Vec2::new(rect.width as f32, rect.height as f32);
+        for (x, y, pixel) in output.enumerate_pixels_mut() {
+            // Test the pixel centre
+            let point = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            if !region.mask.contains(point, size) {
+                *pixel = Rgba([0, 0, 0, 0]);
+            }
+        }
+    }
+    output
+}
+
+/// Writes `image` to `path` in the format given by its extension, or as PNG to stdout if `path` is `-`.
+/// PNGs carry the metadata as text chunks.
+pub fn save(image: &RgbaImage, path: &Path, metadata: Option<&Metadata>) -> anyhow::Result<()> {
+    if path == Path::new("-") {
+        let mut png = Vec::new();
+        metadata::write_png(image, metadata, &mut png)?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&png)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    match ImageFormat::from_path(path).ok() {
+        Some(ImageFormat::Png) => {}
+        // Formats without an alpha channel drop it
+        Some(ImageFormat::Jpeg | ImageFormat::Pnm) => {
+            return DynamicImage::ImageRgba8(image.clone())
+                .to_rgb8()
+                .save(path)
+                .with_context(|| format!("Could not save image to {}", path.display()));
+        }
+        _ => {
+            return image
+                .save(path)
+                .with_context(|| format!("Could not save image to {}", path.display()));
+        }
+    }
+    let file = File::create(path)
+        .with_context(|| format!("Could not save image to {}", path.display()))?;
+    metadata::write_png(image, metadata, BufWriter::new(file))
+        .with_context(|| format!("Could not save image to {}", path.display()))
+}
+
+pub fn copy_text(text: &str) -> anyhow::Result<()> {
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+/// Copies `image`, as a PNG carrying `metadata` if there is any and a clipboard tool is
+/// installed. Otherwise the clipboard gets raw pixels, which lose the metadata.
+pub fn copy(image: RgbaImage, metadata: Option<&Metadata>) -> anyhow::Result<()> {
+    if metadata.is_some() {
+        let mut png = Vec::new();
+        metadata::write_png(&image, metadata, &mut png)?;
+        if copy_png(&png).is_ok() {
+            return Ok(());
+        }
+    }
+    let mut clipboard = arboard::Clipboard::new()?;
+    clipboard.set_image(arboard::ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: std::borrow::Cow::Owned(image.into_raw()),
+    })?;
+    Ok(())
+}
+
+/// Offers `png` as `image/png` through `wl-copy` or `xclip`, which keep serving it after
+/// cleave exits. arboard only takes raw pixels and encodes them itself.
+#[cfg(target_os = "linux")]
+fn copy_png(png: &[u8]) -> anyhow::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let mut command = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut command = Command::new("wl-copy");
+        command.args(["--type", "image/png"]);
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-target", "image/png", "-in"]);
+        command
+    };
+    // The tools stay in the background, they must not hold on to our output
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().context("No stdin")?.write_all(png)?;
+    let status = child.wait()?;
+    anyhow::ensure!(status.success(), "Copying failed with {}", status);
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_png(_: &[u8]) -> anyhow::Result<()> {
+    anyhow::bail!("No clipboard tool for PNGs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn intersects_rects() {
+        let screen = rect(0, 0, 100, 100);
+        assert_eq!(
+            rect(90, 50, 20, 80).intersect(&screen),
+            Some(rect(90, 50, 10, 50))
+        );
+        assert_eq!(
+            rect(10, 10, 5, 5).intersect(&screen),
+            Some(rect(10, 10, 5, 5))
+        );
+        assert_eq!(rect(100, 0, 10, 10).intersect(&screen), None);
+        assert_eq!(rect(10, 10, 0, 5).intersect(&screen), None);
+        assert_eq!(rect(u32::MAX, 0, 10, 10).intersect(&screen), None);
+        assert_eq!(
+            rect(90, 90, u32::MAX, u32::MAX).intersect(&screen),
+            Some(rect(90, 90, 10, 10))
+        );
+    }
+
+    fn region(rect: Rect, mask: Mask) -> Region {
+        Region { rect, mask }
+    }
+
+    #[test]
+    fn masks_ellipses_and_lassos() {
+        let image = RgbaImage::from_pixel(100, 100, Rgba([200, 30, 30, 255]));
+        let ellipse = compose(
+            &image,
+            &[region(rect(10, 10, 40, 20), Mask::Ellipse)],
+            Layout::Original,
+        )
+        .unwrap();
+        assert_eq!(ellipse.dimensions(), (40, 20));
+        for (x, y) in [(0, 0), (39, 0), (0, 19), (39, 19)] {
+            assert_eq!(ellipse.get_pixel(x, y)[3], 0, "{:?}", (x, y));
+        }
+        assert_eq!(ellipse.get_pixel(20, 10)[3], 255);
+        assert_eq!(ellipse.get_pixel(1, 10)[3], 255);
+
+        // A triangle with its right angle at the top left
+        let points = vec![Vec2::ZERO, Vec2::new(30.0, 0.0), Vec2::new(0.0, 30.0)];
+        let lasso = compose(
+            &image,
+            &[region(rect(50, 50, 30, 30), Mask::Polygon(points))],
+            Layout::Original,
+        )
+        .unwrap();
+        assert_eq!(lasso.dimensions(), (30, 30));
+        assert_eq!(lasso.get_pixel(5, 5)[3], 255);
+        assert_eq!(lasso.get_pixel(25, 25)[3], 0);
+        assert_eq!(lasso.get_pixel(29, 0)[3], 0);
+    }
+
+    #[test]
+    fn lays_out_several_regions() {
+        let image = RgbaImage::from_fn(100, 100, |x, _| Rgba([x as u8, 0, 0, 255]));
+        let regions = [
+            region(rect(60, 10, 20, 10), Mask::None),
+            region(rect(10, 40, 10, 30), Mask::None),
+        ];
+        let original = compose(&image, &regions, Layout::Original).unwrap();
+        assert_eq!(original.dimensions(), (70, 60));
+        assert_eq!(*original.get_pixel(50, 0), Rgba([60, 0, 0, 255]));
+        assert_eq!(*original.get_pixel(0, 30), Rgba([10, 0, 0, 255]));
+        assert_eq!(original.get_pixel(20, 10)[3], 0); // The gap between them
+        let packed = compose(&image, &regions, Layout::Packed).unwrap();
+        assert_eq!(packed.dimensions(), (30, 30));
+        assert_eq!(*packed.get_pixel(0, 0), Rgba([60, 0, 0, 255]));
+        assert_eq!(*packed.get_pixel(20, 0), Rgba([10, 0, 0, 255]));
+        assert_eq!(packed.get_pixel(0, 20)[3], 0); // Below the shorter one
+                                                   // Empty regions are left out
+        let with_empty = [regions[0].clone(), region(rect(0, 0, 0, 50), Mask::None)];
+        assert_eq!(
+            compose(&image, &with_empty, Layout::Packed)
+                .unwrap()
+                .dimensions(),
+            (20, 10)
+        );
+    }
+
+    #[test]
+    fn saves_formats_without_alpha() {
+        let path = std::env::temp_dir().join(format!("cleave-save-{}.jpg", std::process::id()));
+        let image = RgbaImage::from_pixel(16, 8, Rgba([200, 40, 40, 255]));
+        save(&image, &path, None).unwrap();
+        let saved = image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((saved.width(), saved.height()), (16, 8));
+        let [r, g, b] = saved.to_rgb8().get_pixel(8, 4).0;
+        assert!(r > 180 && g < 70 && b < 70, "{:?}", (r, g, b));
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::SystemTime;
+
+use anyhow::Context;
+use image::RgbaImage;
+
+use crate::{
+    capture::{Capture, Source},
+    export::Rect,
+    presets,
+    state::LastRegion,
+};
+
+/// A region that is known up front, so it can be captured without showing the overlay.
+#[derive(Clone, Debug)]
+pub enum Region {
+    /// The region that was captured last
+    Last,
+    /// A saved preset, by name
+    Preset(String),
+}
+
+/// What a non-interactive capture took.
+pub struct Shot {
+    pub image: RgbaImage,
+    pub monitor: String,
+    pub rect: Rect,
+    pub scale_factor: f32,
+    pub captured_at: SystemTime,
+}
+
+impl Region {
+    /// Captures the region as it is on screen right now. Presets relative to a window are
+    /// looked up again every time, so they follow the window.
+    pub fn capture(&self, source: &Source) -> anyhow::Result<Shot> {
+        let (capture, rect) = match self {
+            Region::Last => {
+                let last = LastRegion::load()?.context("No region has been captured yet")?;
+                (
+                    Capture::of_output(source, Some(last.monitor_id))?,
+                    last.rect,
+                )
+            }
+            Region::Preset(name) => {
+                let preset = presets::get(name)?;
+                let capture = Capture::of_output(source, preset.monitor_id)?;
+                let rect = preset.resolve(&capture.windows)?;
+                (capture, rect)
+            }
+        };
+        Shot::new(capture, rect)
+    }
+}
+
+impl Shot {
+    /// Takes `rect` out of `capture`.
+    pub fn new(capture: Capture, rect: Rect) -> anyhow::Result<Self> {
+        Ok(Self {
+            image: capture.region(rect)?,
+            monitor: capture.monitor,
+            rect,
+            scale_factor: capture.scale_factor,
+            captured_at: capture.captured_at,
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{context::MoveMode, export::Rect, selection::Shape};
+
+/// A request to a running daemon, sent as one JSON object per line, e.g. `{"cmd": "capture"}`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// Take a screenshot and show the overlay
+    Capture,
+    /// Replace the selection with a rectangle
+    SetSelection {
+        #[serde(flatten)]
+        rect: Rect,
+    },
+    /// Switch how the arrow keys change the selection
+    Mode { mode: MoveMode },
+    /// Switch the shape of the next selection
+    Shape { shape: ShapeKind },
+    /// Copy the selection to the clipboard and end the capture
+    Copy,
+    /// Save the selection to a file and end the capture
+    Save { path: std::path::PathBuf },
+    /// End the capture without copying anything
+    Cancel,
+    /// Record the selection as an animation until `stop`
+    Record,
+    /// Stop recording and save the animation
+    Stop,
+    /// Report whether a capture is in progress and what is selected
+    Query,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeKind {
+    Rect,
+    Ellipse,
+    Lasso,
+}
+
+impl From<ShapeKind> for Shape {
+    fn from(kind: ShapeKind) -> Self {
+        match kind {
+            ShapeKind::Rect => Shape::Rect,
+            ShapeKind::Ellipse => Shape::Ellipse,
+            ShapeKind::Lasso => Shape::Lasso(Vec::new()),
+        }
+    }
+}
+
+/// The reply to every command, sent as one JSON object per line.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capturing: Option<bool>,
+    /// Every selected region, ending with the active selection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regions: Option<Vec<Rect>>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn error(err: impl std::fmt::Display) -> Self {
+        Self {
+            ok: false,
+            error: Some(err.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<anyhow::Result<()>> for Response {
+    fn from(result: anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => Self::ok(),
+            Err(err) => Self::error(format!("{:#}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        let command: Command = serde_json::from_str(
+            r#"{"cmd": "set_selection", "x": 1, "y": 2, "width": 3, "height": 4}"#,
+        )
+        .unwrap();
+        let Command::SetSelection { rect } = command else {
+            panic!("Expected set_selection, got {:?}", command);
+        };
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (1, 2, 3, 4));
+        assert!(serde_json::from_str::<Command>(r#"{"cmd": "explode"}"#).is_err());
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let json = serde_json::to_string(&Response::error("Nothing selected")).unwrap();
+        assert_eq!(json, r#"{"ok":false,"error":"Nothing selected"}"#);
+        let response: Response = serde_json::from_str(&json).unwrap();
+        assert!(!response.ok);
+        assert!(
+            serde_json::from_str::<Response>(r#"{"ok":true}"#)
+                .unwrap()
+                .ok
+        );
+    }
+}
@@ -1,85 +1,402 @@
 #![windows_subsystem = "windows"]
 
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use clap::Parser;
+use image::RgbaImage;
 use winit::{
     application::ApplicationHandler,
     event::{ElementState, KeyEvent, MouseButton, WindowEvent},
-    keyboard::{Key, NamedKey},
+    event_loop::{ActiveEventLoop, EventLoopProxy},
+    keyboard::{Key, ModifiersState, NamedKey},
 };
 
+mod animation;
+mod archive;
+mod beautify;
+mod browser;
+mod capture;
+mod cli;
+mod codes;
+mod config;
 mod context;
+#[cfg(unix)]
+mod daemon;
+mod export;
+mod headless;
+mod ipc;
+mod label;
+mod metadata;
+mod outcome;
+mod pin;
+mod presets;
+mod record;
+mod scale;
+mod scrolling;
+mod selection;
+mod snap;
+mod state;
+mod stitch;
+mod timelapse;
+mod trim;
+mod undo;
+use browser::HistoryBrowser;
+use capture::Source;
+use cli::{Action, Args};
+use config::Config;
 use context::{AppContext, Direction, MoveMode};
+use export::Rect;
+use ipc::{Command, Response};
+use metadata::Metadata;
+use outcome::{Outcome, Report};
+use pin::PinWindow;
+use record::Indicator;
+use scale::Scaling;
+use selection::Shape;
+use timelapse::Timelapse;
 
-pub struct Drag {
-    start: (f64, f64),
-    end: Option<(f64, f64)>,
+// Space between a selection and the recording indicator
+const INDICATOR_GAP: u32 = 16;
+
+/// Requests sent to the event loop from other threads.
+#[derive(Debug)]
+pub enum AppEvent {
+    Command(Command, std::sync::mpsc::Sender<Response>),
+    /// A scrolling capture has finished
+    Scrolled(anyhow::Result<RgbaImage>),
+    /// A recording has been saved
+    Recorded(anyhow::Result<PathBuf>),
 }
 
-impl Drag {
-    fn coords(&self) -> Option<((u32, u32), (u32, u32))> {
-        let end = self.end?;
-        let (start_x, start_y) = (self.start.0 as u32, self.start.1 as u32);
-        let (end_x, end_y) = (end.0 as u32, end.1 as u32);
+struct App {
+    context: Option<AppContext>,
+    pins: Vec<PinWindow>,
+    browser: Option<HistoryBrowser>,
+    indicator: Option<Indicator>, // Shown while recording
+    modifiers: ModifiersState,
+    daemon: bool, // Keep the overlay around after a capture instead of exiting
+    save_preset: Option<String>, // Also save the delivered selection under this name
+    relative: bool, // Make the saved preset relative to the window it is on
+    browse: bool, // Open the history browser instead of capturing
+    source: Source,
+    output: Option<PathBuf>, // Save here instead of copying to the clipboard
+    copy: bool,              // Copy to the clipboard even when saving
+    scaling: Scaling,
+    strip_metadata: bool, // Don't embed where the capture came from into saved or copied PNGs
+    started: Instant,
+    report: Option<Report>,
+    proxy: EventLoopProxy<AppEvent>,
+}
 
-        let (min_x, max_x) = (start_x.min(end_x), start_x.max(end_x));
-        let (min_y, max_y) = (start_y.min(end_y), start_y.max(end_y));
-        Some(((min_x, min_y), (max_x, max_y)))
+/// Copies the image, or saves it if an output path was given.
+fn write_image(
+    image: RgbaImage,
+    output: Option<&Path>,
+    copy: bool,
+    metadata: Option<&Metadata>,
+) -> anyhow::Result<Outcome> {
+    let Some(path) = output else {
+        export::copy(image, metadata)?;
+        return Ok(Outcome::Copied);
+    };
+    export::save(&image, path, metadata)?;
+    if copy {
+        export::copy(image, metadata)?;
     }
+    Ok(Outcome::Saved)
+}
+
+/// Copies or saves the selection, and keeps it in the history.
+fn deliver(
+    context: &AppContext,
+    output: Option<&Path>,
+    copy: bool,
+    scaling: &Scaling,
+    strip_metadata: bool,
+) -> anyhow::Result<Outcome> {
+    let image = context.selected_image().context("Nothing selected")?;
+    deliver_image(context, image, output, copy, scaling, strip_metadata)
 }
 
-pub struct Selection {
-    start: (f64, f64),
-    end: (f64, f64),
+/// Copies or saves an image taken of the selection, and keeps it in the history.
+fn deliver_image(
+    context: &AppContext,
+    image: RgbaImage,
+    output: Option<&Path>,
+    copy: bool,
+    scaling: &Scaling,
+    strip_metadata: bool,
+) -> anyhow::Result<Outcome> {
+    let image = scaling.apply(image, context.scale_factor());
+    let image = context.styled(image)?;
+    let metadata = (!strip_metadata).then(|| context.metadata());
+    let outcome = write_image(image.clone(), output, copy, metadata.as_ref())?;
+    if let Err(err) = context.record_selection(&image) {
+        eprintln!("Could not add capture to history: {:?}", err);
+    }
+    Ok(outcome)
 }
 
-impl Selection {
-    fn dimensions(&self) -> (f64, f64) {
-        let width = (self.end.0 - self.start.0).abs();
-        let height = (self.end.1 - self.start.1).abs();
-        (width, height)
+/// Captures a known region straight away, without showing the overlay.
+fn capture_headless(
+    region: &headless::Region,
+    source: &Source,
+    output: Option<&Path>,
+    copy: bool,
+    scaling: &Scaling,
+    strip_metadata: bool,
+    started: Instant,
+) -> anyhow::Result<Report> {
+    let shot = region.capture(source)?;
+    let metadata = (!strip_metadata).then(|| {
+        Metadata::new(
+            &shot.monitor,
+            Some(shot.rect),
+            shot.scale_factor,
+            shot.captured_at,
+        )
+    });
+    let config = Config::load_or_default();
+    let image = scaling.apply(shot.image, shot.scale_factor);
+    let image = config
+        .beautify
+        .apply(image, config.beautify.style.as_deref())?;
+    let outcome = write_image(image.clone(), output, copy, metadata.as_ref())?;
+    if let Err(err) = archive::record(&config.history, &image, &shot.monitor, Some(shot.rect)) {
+        eprintln!("Could not add capture to history: {:?}", err);
     }
+    Ok(Report {
+        selection: Some(shot.rect),
+        monitor: Some(shot.monitor),
+        output: output.map(Path::to_path_buf),
+        ..Report::new(outcome, None, started)
+    })
+}
 
-    fn area(&self) -> f64 {
-        let (width, height) = self.dimensions();
-        width * height
+/// Ends the current capture, a daemon keeps running in the background.
+fn end_capture(daemon: bool, context: &mut AppContext, event_loop: &ActiveEventLoop) {
+    context.hide_window();
+    if !daemon {
+        event_loop.exit();
     }
+}
 
-    // fn aspect_ratio(&self) -> f64 {
-    //     let (width, height) = self.dimensions();
-    //     width / height
-    // }
+impl App {
+    /// Whether nothing is left on screen that should keep cleave running.
+    fn is_idle(&self) -> bool {
+        !self.daemon && self.context.is_none() && self.pins.is_empty() && self.browser.is_none()
+    }
 
-    // fn center(&self) -> (f64, f64) {
-    //     let x = (self.start.0 + self.end.0) / 2.0;
-    //     let y = (self.start.1 + self.end.1) / 2.0;
-    //     (x, y)
-    // }
+    /// Hides the overlay and records the selection in the background until stopped.
+    fn start_recording(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
+        let Some(context) = &mut self.context else {
+            anyhow::bail!("The overlay is not running");
+        };
+        if self.indicator.is_some() {
+            anyhow::bail!("Already recording");
+        }
+        let rect = Rect::bounds(&context.selected_rects()).context("Nothing selected")?;
 
-    fn coords(&self) -> ((u32, u32), (u32, u32)) {
-        let (start_x, start_y) = (self.start.0, self.start.1);
-        let (end_x, end_y) = (self.end.0, self.end.1);
+        // Next to the selection so it isn't recorded, on the left if there is no room on the right
+        let right = context.screen_position(rect.right() + INDICATOR_GAP, rect.y);
+        let left = context.screen_position(rect.x.saturating_sub(INDICATOR_GAP * 2), rect.y);
+        let fits = event_loop.primary_monitor().is_none_or(|m| {
+            right.x + (INDICATOR_GAP as i32) < m.position().x + m.size().width as i32
+        });
+        let indicator = Indicator::new(event_loop, if fits { right } else { left })?;
+        context.hide_window();
 
-        let (min_x, max_x) = (start_x.min(end_x).ceil(), start_x.max(end_x).floor());
-        let (min_y, max_y) = (start_y.min(end_y).ceil(), start_y.max(end_y).floor());
-        ((min_x as u32, min_y as u32), (max_x as u32, max_y as u32))
+        let (source, monitor_id) = (context.source().clone(), context.monitor_id());
+        let config = context.record_config().clone();
+        let stop = indicator.stop.clone();
+        let output = self.output.clone();
+        let proxy = self.proxy.clone();
+        std::thread::spawn(move || {
+            let result =
+                record::record(&source, monitor_id, rect, &config, output.as_deref(), &stop);
+            let _ = proxy.send_event(AppEvent::Recorded(result));
+        });
+        self.indicator = Some(indicator);
+        Ok(())
     }
-}
 
-struct App {
-    context: Option<AppContext>,
+    fn handle_command(&mut self, command: Command, event_loop: &ActiveEventLoop) -> Response {
+        match (command, self.context.as_mut()) {
+            (Command::Record, _) => self.start_recording(event_loop).into(),
+            (Command::Stop, _) => match self.indicator.take() {
+                Some(indicator) => {
+                    indicator.stop();
+                    Response::ok()
+                }
+                None => Response::error("Not recording"),
+            },
+            (_, None) => Response::error("The overlay is not running"),
+            (Command::Capture, Some(context)) if context.is_visible() => Response::ok(),
+            (Command::Capture, Some(context)) => context.recapture().into(),
+            (Command::SetSelection { rect }, Some(context)) => context.set_selection(rect).into(),
+            (Command::Mode { mode }, Some(context)) => {
+                context.set_mode(mode);
+                Response::ok()
+            }
+            (Command::Shape { shape }, Some(context)) => {
+                context.set_shape(shape.into());
+                Response::ok()
+            }
+            (Command::Copy, Some(context)) => {
+                let result =
+                    deliver(context, None, false, &self.scaling, self.strip_metadata).map(|_| ());
+                end_capture(self.daemon, context, event_loop);
+                result.into()
+            }
+            (Command::Save { path }, Some(context)) => {
+                let result = deliver(
+                    context,
+                    Some(&path),
+                    false,
+                    &self.scaling,
+                    self.strip_metadata,
+                )
+                .map(|_| ());
+                end_capture(self.daemon, context, event_loop);
+                result.into()
+            }
+            (Command::Cancel, Some(context)) => {
+                end_capture(self.daemon, context, event_loop);
+                Response::ok()
+            }
+            (Command::Query, Some(context)) => Response {
+                capturing: Some(context.is_visible()),
+                regions: Some(context.selected_rects()),
+                ..Response::ok()
+            },
+        }
+    }
 }
 
-impl ApplicationHandler for App {
-    fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        let context = AppContext::new(event_loop).expect("Could not start context");
-        self.context = Some(context);
+impl ApplicationHandler<AppEvent> for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.context.is_some() || self.browser.is_some() {
+            return;
+        }
+        if self.browse {
+            match HistoryBrowser::new(event_loop) {
+                Ok(browser) => self.browser = Some(browser),
+                Err(err) => {
+                    eprintln!("Could not open the capture history: {:?}", err);
+                    self.report = Some(Report::failed(&err, None, self.started));
+                    event_loop.exit();
+                }
+            }
+            return;
+        }
+        match AppContext::new(event_loop, !self.daemon, self.source.clone()) {
+            Ok(context) => self.context = Some(context),
+            Err(err) => {
+                eprintln!("Could not start context: {:?}", err);
+                self.report = Some(Report::failed(&err, None, self.started));
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
+        match event {
+            AppEvent::Command(command, reply) => {
+                let response = self.handle_command(command, event_loop);
+                let _ = reply.send(response);
+            }
+            AppEvent::Scrolled(result) => {
+                let Some(context) = &mut self.context else {
+                    return;
+                };
+                let delivered = result.and_then(|image| {
+                    deliver_image(
+                        context,
+                        image,
+                        self.output.as_deref(),
+                        self.copy,
+                        &self.scaling,
+                        self.strip_metadata,
+                    )
+                });
+                let report = match delivered {
+                    Ok(outcome) => Report {
+                        output: self.output.clone(),
+                        ..Report::new(outcome, Some(context), self.started)
+                    },
+                    Err(err) => {
+                        eprintln!("Could not capture while scrolling: {:?}", err);
+                        Report::failed(&err, Some(context), self.started)
+                    }
+                };
+                self.report = Some(report);
+                end_capture(self.daemon, context, event_loop);
+            }
+            AppEvent::Recorded(result) => {
+                self.indicator = None;
+                let Some(context) = &mut self.context else {
+                    return;
+                };
+                let report = match result {
+                    Ok(path) => Report {
+                        output: Some(path),
+                        ..Report::new(Outcome::Saved, Some(context), self.started)
+                    },
+                    Err(err) => {
+                        eprintln!("Could not record selection: {:?}", err);
+                        Report::failed(&err, Some(context), self.started)
+                    }
+                };
+                self.report = Some(report);
+                end_capture(self.daemon, context, event_loop);
+            }
+        }
     }
 
     fn window_event(
         &mut self,
-        event_loop: &winit::event_loop::ActiveEventLoop,
+        event_loop: &ActiveEventLoop,
         id: winit::window::WindowId,
         event: winit::event::WindowEvent,
     ) {
+        if let Some(index) = self.pins.iter().position(|pin| pin.window_id() == id) {
+            if !self.pins[index].handle_event(event) {
+                self.pins.remove(index);
+                if self.is_idle() {
+                    event_loop.exit();
+                }
+            }
+            return;
+        }
+        if let Some(indicator) = self.indicator.as_mut().filter(|i| i.window_id() == id) {
+            if !indicator.handle_event(event) {
+                // The recording thread reports back once the animation is saved
+                indicator.stop();
+                self.indicator = None;
+            }
+            return;
+        }
+        if let Some(browser) = self.browser.as_mut().filter(|b| b.window_id() == id) {
+            if !browser.handle_event(event) {
+                if let Some((outcome, output)) = browser.result.take() {
+                    self.report = Some(Report {
+                        output,
+                        ..Report::new(outcome, None, self.started)
+                    });
+                }
+                self.browser = None;
+                if self.is_idle() {
+                    event_loop.exit();
+                }
+            }
+            return;
+        }
+
         let Some(context) = &mut self.context else {
             return;
         };
@@ -91,9 +408,51 @@ impl ApplicationHandler for App {
             WindowEvent::RedrawRequested => {
                 context.draw();
             }
+            WindowEvent::Resized(size) => {
+                context.resize(size);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
             WindowEvent::CursorMoved { position, .. } => {
                 context.update_mouse_position(position.x, position.y);
             }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: key,
+                        ..
+                    },
+                ..
+            } if context.is_picking_preset() => {
+                let choice = match key {
+                    Key::Named(NamedKey::Escape | NamedKey::Tab) => {
+                        context.close_preset_picker();
+                        None
+                    }
+                    Key::Named(NamedKey::ArrowUp) => {
+                        context.move_preset_highlight(-1);
+                        None
+                    }
+                    Key::Named(NamedKey::ArrowDown) => {
+                        context.move_preset_highlight(1);
+                        None
+                    }
+                    Key::Named(NamedKey::Enter) => Some(None),
+                    Key::Character(c) => match c.parse::<usize>() {
+                        Ok(number @ 1..=9) => Some(Some(number - 1)),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(index) = choice {
+                    if let Err(err) = context.choose_preset(index) {
+                        eprintln!("Could not select preset: {:?}", err);
+                        context.notify(format!("{:#}", err));
+                    }
+                }
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -104,13 +463,40 @@ impl ApplicationHandler for App {
                 ..
             } => match (state, key) {
                 (ElementState::Pressed, Key::Named(NamedKey::Escape)) => {
-                    event_loop.exit();
-                    context.destroy();
+                    if !self.daemon {
+                        context.destroy();
+                    }
+                    self.report =
+                        Some(Report::new(Outcome::Cancelled, Some(context), self.started));
+                    end_capture(self.daemon, context, event_loop);
                 }
                 (ElementState::Pressed, Key::Named(NamedKey::Space)) => {
                     context.hide_window();
-                    context.save_selection_to_clipboard();
-                    event_loop.exit();
+                    let report = match deliver(
+                        context,
+                        self.output.as_deref(),
+                        self.copy,
+                        &self.scaling,
+                        self.strip_metadata,
+                    ) {
+                        Ok(outcome) => {
+                            if let Some(name) = &self.save_preset {
+                                if let Err(err) = context.save_preset(name, self.relative) {
+                                    eprintln!("Could not save preset {}: {:?}", name, err);
+                                }
+                            }
+                            Report {
+                                output: self.output.clone(),
+                                ..Report::new(outcome, Some(context), self.started)
+                            }
+                        }
+                        Err(err) => {
+                            eprintln!("Could not copy selection: {:?}", err);
+                            Report::failed(&err, Some(context), self.started)
+                        }
+                    };
+                    self.report = Some(report);
+                    end_capture(self.daemon, context, event_loop);
                 }
                 (ElementState::Pressed, Key::Named(NamedKey::ArrowDown)) => {
                     context.handle_move(Direction::Down);
@@ -136,25 +522,297 @@ impl ApplicationHandler for App {
                 (ElementState::Released, Key::Named(NamedKey::Control)) => {
                     context.set_mode(MoveMode::Resize);
                 }
+                (ElementState::Pressed, Key::Named(NamedKey::Alt)) => {
+                    context.set_snapping(false);
+                }
+                (ElementState::Released, Key::Named(NamedKey::Alt)) => {
+                    context.set_snapping(true);
+                }
+                (ElementState::Pressed, Key::Character(c))
+                    if self.modifiers.control_key() && c.eq_ignore_ascii_case("z") =>
+                {
+                    if self.modifiers.shift_key() {
+                        context.redo();
+                    } else {
+                        context.undo();
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("r") => {
+                    context.set_shape(Shape::Rect);
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("e") => {
+                    context.set_shape(Shape::Ellipse);
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("l") => {
+                    context.set_shape(Shape::Lasso(Vec::new()));
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("m") => {
+                    context.toggle_cursor();
+                }
+                (ElementState::Pressed, Key::Named(NamedKey::Tab)) => {
+                    if let Err(err) = context.open_preset_picker() {
+                        eprintln!("Could not list presets: {:?}", err);
+                        context.notify(format!("{:#}", err));
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c == "." => {
+                    if let Err(err) = context.restore_last_selection() {
+                        eprintln!("Could not restore the last region: {:?}", err);
+                        context.notify(format!("{:#}", err));
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("s") => {
+                    let Some(rect) = Rect::bounds(&context.selected_rects()) else {
+                        return;
+                    };
+                    // Out of the way while scrolling, the result arrives as an event
+                    context.hide_window();
+                    let (source, monitor_id) = (context.source().clone(), context.monitor_id());
+                    let proxy = self.proxy.clone();
+                    std::thread::spawn(move || {
+                        let result = scrolling::capture(&source, monitor_id, rect);
+                        let _ = proxy.send_event(AppEvent::Scrolled(result));
+                    });
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("b") => {
+                    let notice = match context.cycle_style() {
+                        Some(style) => format!("Style: {}", style),
+                        None => "No style".to_string(),
+                    };
+                    context.notify(notice);
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("t") => {
+                    if let Err(err) = context.trim_selection() {
+                        context.notify(format!("{:#}", err));
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("q") => {
+                    let texts = context.scan_codes();
+                    let notice = if texts.is_empty() {
+                        "No QR code found".to_string()
+                    } else {
+                        match export::copy_text(&texts.join("\n")) {
+                            Ok(()) => format!("Copied {} decoded QR code(s)", texts.len()),
+                            Err(err) => format!("Could not copy decoded text: {:#}", err),
+                        }
+                    };
+                    context.notify(notice);
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("v") => {
+                    if let Err(err) = self.start_recording(event_loop) {
+                        eprintln!("Could not start recording: {:?}", err);
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("h") => {
+                    match HistoryBrowser::new(event_loop) {
+                        Ok(browser) => {
+                            context.hide_window();
+                            self.browser = Some(browser);
+                            if !self.daemon {
+                                self.context = None;
+                            }
+                        }
+                        Err(err) => context.notify(format!("{:#}", err)),
+                    }
+                }
+                (ElementState::Pressed, Key::Character(c)) if c.eq_ignore_ascii_case("p") => {
+                    let Some(image) = context.selected_image() else {
+                        return;
+                    };
+                    context.hide_window();
+                    match PinWindow::new(event_loop, image) {
+                        Ok(pin) => {
+                            self.pins.push(pin);
+                            self.report =
+                                Some(Report::new(Outcome::Pinned, Some(context), self.started));
+                        }
+                        Err(err) => {
+                            eprintln!("Could not pin selection: {:?}", err);
+                            self.report = Some(Report::failed(&err, Some(context), self.started));
+                            end_capture(self.daemon, context, event_loop);
+                        }
+                    }
+                    if !self.daemon {
+                        self.context = None;
+                    }
+                }
                 _ => {}
             },
             WindowEvent::MouseInput { state, button, .. } => match (state, button) {
-                (ElementState::Pressed, MouseButton::Left) => context.start_drag(),
+                (ElementState::Pressed, MouseButton::Left) => {
+                    context.start_drag(self.modifiers.control_key())
+                }
                 (ElementState::Released, MouseButton::Left) => context.end_drag(),
-                (_, MouseButton::Right) => context.cancel_drag(),
+                (ElementState::Pressed, MouseButton::Right) => context.cancel_drag(),
                 _ => {}
             },
             WindowEvent::CloseRequested => {
-                event_loop.exit();
+                self.report = Some(Report::new(Outcome::Cancelled, Some(context), self.started));
+                end_capture(self.daemon, context, event_loop);
             }
             _ => {}
         }
     }
 }
 
-fn main() -> anyhow::Result<()> {
-    let mut app = App { context: None };
-    let event_loop = winit::event_loop::EventLoop::new()?;
+fn main() -> ExitCode {
+    let started = Instant::now();
+    let args = Args::parse();
+    run(&args, started).unwrap_or_else(|err| {
+        eprintln!("Error: {:?}", err);
+        let report = Report::failed(&err, None, started);
+        finish(&report, args.json, args.output().as_deref())
+            .unwrap_or_else(|_| Outcome::Failed.exit_code())
+    })
+}
+
+fn run(args: &Args, started: Instant) -> anyhow::Result<ExitCode> {
+    if let Some(Action::Inspect { file }) = &args.action {
+        let chunks = metadata::read(file)?;
+        if chunks.is_empty() {
+            eprintln!("{} has no metadata", file.display());
+            return Ok(Outcome::Failed.exit_code());
+        }
+        for (keyword, text) in chunks {
+            println!("{}: {}", keyword, text);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    #[cfg(unix)]
+    {
+        if let Some(command) = &args.ctl {
+            let reply = daemon::send(command)?
+                .ok_or_else(|| anyhow::anyhow!("No cleave daemon is running"))?;
+            println!("{}", reply);
+            let ok = serde_json::from_str::<Response>(&reply).is_ok_and(|r| r.ok);
+            return Ok(if ok {
+                ExitCode::SUCCESS
+            } else {
+                Outcome::Failed.exit_code()
+            });
+        }
+        if args.forwardable() && daemon::request_capture()? {
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if args.daemon || args.ctl.is_some() {
+            anyhow::bail!("Daemon mode is only supported on Unix");
+        }
+    }
+
+    let output = args.output();
+    let source = match &args.image {
+        Some(path) => Source::file(path)?,
+        None if args.clipboard => Source::clipboard()?,
+        None if args.mock => Source::Mock,
+        None => Source::Screen,
+    };
+    let headless = match &args.preset {
+        Some(name) => Some(headless::Region::Preset(name.clone())),
+        None if args.last => Some(headless::Region::Last),
+        None => None,
+    };
+    if let (Some(region), Some(interval)) = (&headless, args.timelapse) {
+        let timelapse = Timelapse {
+            interval,
+            duration: args.duration,
+            animate: args.animate,
+            scaling: args.scaling(),
+            metadata: !args.strip_metadata,
+        };
+        let dir = match &output {
+            Some(dir) => dir.clone(),
+            None => default_timelapse_dir()?,
+        };
+        let report = match timelapse.run(
+            || region.capture(&source),
+            &dir,
+            &Config::load_or_default().record,
+        ) {
+            Ok(path) => Report {
+                output: Some(path),
+                ..Report::new(Outcome::Saved, None, started)
+            },
+            Err(err) => {
+                eprintln!("Could not finish timelapse: {:?}", err);
+                Report::failed(&err, None, started)
+            }
+        };
+        return finish(&report, args.json, None);
+    }
+    if let Some(region) = headless {
+        let report = capture_headless(
+            &region,
+            &source,
+            output.as_deref(),
+            args.copy,
+            &args.scaling(),
+            args.strip_metadata,
+            started,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Could not capture the region: {:?}", err);
+            Report::failed(&err, None, started)
+        });
+        return finish(&report, args.json, output.as_deref());
+    }
+
+    let event_loop = winit::event_loop::EventLoop::with_user_event().build()?;
+    let mut app = App {
+        context: None,
+        pins: Vec::new(),
+        browser: None,
+        indicator: None,
+        modifiers: ModifiersState::empty(),
+        daemon: args.daemon,
+        save_preset: args.save_preset.clone(),
+        relative: args.relative,
+        browse: args.history,
+        source,
+        output: output.clone(),
+        copy: args.copy,
+        scaling: args.scaling(),
+        strip_metadata: args.strip_metadata,
+        started,
+        report: None,
+        proxy: event_loop.create_proxy(),
+    };
+    #[cfg(unix)]
+    {
+        if args.daemon {
+            daemon::listen(event_loop.create_proxy())?;
+        }
+    }
     event_loop.run_app(&mut app)?;
-    Ok(())
+
+    let report = app
+        .report
+        .unwrap_or_else(|| Report::new(Outcome::Cancelled, None, started));
+    finish(&report, args.json, output.as_deref())
+}
+
+/// `cleave-timelapse-<timestamp>` in the pictures directory.
+fn default_timelapse_dir() -> anyhow::Result<PathBuf> {
+    let dir = dirs::picture_dir()
+        .or_else(dirs::home_dir)
+        .context("Could not find a directory for the timelapse")?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(dir.join(format!("cleave-timelapse-{}", timestamp)))
+}
+
+/// Prints the report if asked to, and turns it into the exit code.
+fn finish(report: &Report, json: bool, output: Option<&Path>) -> anyhow::Result<ExitCode> {
+    if json {
+        let json = serde_json::to_string(report)?;
+        // Keep stdout clean when the image is piped through it
+        if output == Some(Path::new("-")) {
+            eprintln!("{}", json);
+        } else {
+            println!("{}", json);
+        }
+    }
+    Ok(report.outcome.exit_code())
 }
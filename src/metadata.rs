@@ -0,0 +1,195 @@
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use image::RgbaImage;
+
+use crate::export::Rect;
+
+/// Where a capture came from, embedded into saved PNGs as text chunks.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub monitor: String,
+    pub rect: Option<Rect>,
+    pub scale_factor: f32,
+    pub timestamp: SystemTime,
+}
+
+impl Metadata {
+    /// Metadata for a capture of `monitor` taken at `timestamp`.
+    pub fn new(
+        monitor: &str,
+        rect: Option<Rect>,
+        scale_factor: f32,
+        timestamp: SystemTime,
+    ) -> Self {
+        Self {
+            monitor: monitor.to_string(),
+            rect,
+            scale_factor,
+            timestamp,
+        }
+    }
+
+    /// The text chunks, using the keywords from the PNG specification where there is one.
+    fn chunks(&self) -> Vec<(&'static str, String)> {
+        let seconds = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut chunks = vec![
+            ("Software", format!("cleave {}", env!("CARGO_PKG_VERSION"))),
+            ("Creation Time", rfc3339(seconds)),
+            ("Source", self.monitor.clone()),
+        ];
+        if let Some(rect) = self.rect {
+            let region = format!("{},{} {}x{}", rect.x, rect.y, rect.width, rect.height);
+            chunks.push(("Region", region));
+        }
+        chunks.push(("Scale Factor", self.scale_factor.to_string()));
+        chunks
+    }
+}
+
+/// Encodes `image` as PNG, with the metadata as text chunks if there is any.
+pub fn write_png(
+    image: &RgbaImage,
+    metadata: Option<&Metadata>,
+    writer: impl Write,
+) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    for (keyword, text) in metadata.map(Metadata::chunks).unwrap_or_default() {
+        // tEXt is Latin-1 only, monitor names can be anything
+        if text.chars().all(|c| (c as u32) < 0x100) {
+            encoder.add_text_chunk(keyword.to_string(), text)?;
+        } else {
+            encoder.add_itxt_chunk(keyword.to_string(), text)?;
+        }
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Every text chunk in the PNG at `path`, as keyword and text.
+pub fn read(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+    let mut reader = png::Decoder::new(BufReader::new(file))
+        .read_info()
+        .with_context(|| format!("{} is not a PNG", path.display()))?;
+    // Chunks after the image data are only seen once it has been read
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buffer)?;
+    reader.finish()?;
+
+    let info = reader.info();
+    let mut chunks: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+    for chunk in &info.compressed_latin1_text {
+        chunks.push((chunk.keyword.clone(), chunk.get_text()?));
+    }
+    for chunk in &info.utf8_text {
+        chunks.push((chunk.keyword.clone(), chunk.get_text()?));
+    }
+    Ok(chunks)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, like `2024-11-05T14:03:27Z`.
+fn rfc3339(seconds: u64) -> String {
+    let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+    // Civil from days, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use image::Rgba;
+
+    use super::*;
+
+    fn roundtrip(metadata: Option<&Metadata>) -> Vec<(String, String)> {
+        let path = std::env::temp_dir().join(format!(
+            "cleave-metadata-{}-{}.png",
+            std::process::id(),
+            metadata.is_some()
+        ));
+        let image = RgbaImage::from_pixel(3, 2, Rgba([10, 20, 30, 255]));
+        write_png(&image, metadata, File::create(&path).unwrap()).unwrap();
+        assert_eq!(image::open(&path).unwrap().to_rgba8(), image);
+        let chunks = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        chunks
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1709210096), "2024-02-29T12:34:56Z");
+        assert_eq!(rfc3339(1735689599), "2024-12-31T23:59:59Z");
+        assert_eq!(rfc3339(1740787200), "2025-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let metadata = Metadata::new(
+            "DP-1",
+            Some(Rect {
+                x: 10,
+                y: 20,
+                width: 300,
+                height: 200,
+            }),
+            1.5,
+            UNIX_EPOCH + Duration::from_secs(1740787200),
+        );
+        let chunks = roundtrip(Some(&metadata));
+        let expected = [
+            ("Software", format!("cleave {}", env!("CARGO_PKG_VERSION"))),
+            ("Creation Time", "2025-03-01T00:00:00Z".to_string()),
+            ("Source", "DP-1".to_string()),
+            ("Region", "10,20 300x200".to_string()),
+            ("Scale Factor", "1.5".to_string()),
+        ]
+        .map(|(keyword, text)| (keyword.to_string(), text));
+        assert_eq!(chunks, expected);
+        assert!(roundtrip(None).is_empty());
+    }
+
+    #[test]
+    fn keeps_names_outside_latin1() {
+        let metadata = Metadata::new("Монитор 東京", None, 1.0, UNIX_EPOCH);
+        let chunks = roundtrip(Some(&metadata));
+        assert!(chunks.contains(&("Source".to_string(), "Монитор 東京".to_string())));
+        assert!(!chunks.iter().any(|(keyword, _)| keyword == "Region"));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{path::PathBuf, process::ExitCode, time::Instant};
+
+use serde::Serialize;
+
+use crate::{context::AppContext, export::Rect};
+
+/// How a capture ended, each with its own exit code.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Copied,
+    Cancelled,
+    Failed,
+    Saved,
+    Pinned,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> ExitCode {
+        ExitCode::from(match self {
+            Outcome::Copied => 0,
+            Outcome::Cancelled => 1,
+            Outcome::Failed => 2,
+            Outcome::Saved => 3,
+            Outcome::Pinned => 4,
+        })
+    }
+}
+
+/// The result of a capture, printed as JSON with `--json`.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub outcome: Outcome,
+    /// Bounds of everything that was selected
+    pub selection: Option<Rect>,
+    pub monitor: Option<String>,
+    pub output: Option<PathBuf>,
+    pub error: Option<String>,
+    pub elapsed_ms: u128,
+}
+
+impl Report {
+    pub fn new(outcome: Outcome, context: Option<&AppContext>, started: Instant) -> Self {
+        Self {
+            outcome,
+            selection: context.and_then(|c| Rect::bounds(&c.selected_rects())),
+            monitor: context.map(|c| c.monitor().to_string()),
+            output: None,
+            error: None,
+            elapsed_ms: started.elapsed().as_millis(),
+        }
+    }
+
+    pub fn failed(err: &anyhow::Error, context: Option<&AppContext>, started: Instant) -> Self {
+        Self {
+            error: Some(format!("{:#}", err)),
+            ..Self::new(Outcome::Failed, context, started)
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+use winit::{
+    dpi::PhysicalSize,
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowAttributes, WindowId, WindowLevel},
+};
+
+use cleave_graphics::prelude::*;
+
+use crate::context::SelectionUniforms;
+
+/// Clicks closer together than this make a double click.
+pub const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 8.0;
+
+/// A capture shown in its own borderless, always on top window.
+pub struct PinWindow {
+    graphics: Graphics<Window>,
+    // Without a selection the overlay shader just draws the texture
+    bundle: GraphicsBundle<SelectionUniforms>,
+    image_size: PhysicalSize<u32>,
+    zoom: f32,
+    last_click: Option<Instant>,
+}
+
+impl PinWindow {
+    pub fn new(event_loop: &ActiveEventLoop, image: RgbaImage) -> anyhow::Result<Self> {
+        let image_size = PhysicalSize::new(image.width(), image.height());
+        let window = event_loop.create_window(
+            WindowAttributes::default()
+                .with_inner_size(image_size)
+                .with_title("Cleave Pin")
+                .with_decorations(false)
+                .with_window_level(WindowLevel::AlwaysOnTop),
+        )?;
+
+        let graphics = Graphics::new(window, image_size.width, image_size.height);
+        let graphics = pollster::block_on(graphics)?;
+        let bundle = GraphicsBundle::new(
+            image.into(),
+            &graphics.device,
+            &graphics.queue,
+            wgpu::PrimitiveTopology::TriangleStrip,
+            graphics.config.format,
+        );
+        graphics.request_redraw();
+
+        Ok(Self {
+            graphics,
+            bundle,
+            image_size,
+            zoom: 1.0,
+            last_click: None,
+        })
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.graphics.id()
+    }
+
+    /// Returns `false` once the pin should be closed.
+    pub fn handle_event(&mut self, event: WindowEvent) -> bool {
+        match event {
+            WindowEvent::RedrawRequested => self.draw(),
+            WindowEvent::Resized(size) => {
+                self.graphics.resize(size.width, size.height);
+                self.graphics.request_redraw();
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if self.last_click.is_some_and(|t| t.elapsed() < DOUBLE_CLICK) {
+                    return false;
+                }
+                self.last_click = Some(Instant::now());
+                let _ = self.graphics.drag_window();
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 40.0,
+                };
+                self.zoom = (self.zoom * 1.1f32.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+                let width = (self.image_size.width as f32 * self.zoom).max(1.0);
+                let height = (self.image_size.height as f32 * self.zoom).max(1.0);
+                let _ = self
+                    .graphics
+                    .request_inner_size(PhysicalSize::new(width as u32, height as u32));
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Escape),
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::CloseRequested => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self) {
+        draw(&mut self.graphics, &self.bundle);
+    }
+}
+
+/// Renders a window that just shows the texture of `bundle`, like pins and the history browser.
+pub fn draw(graphics: &mut Graphics<Window>, bundle: &GraphicsBundle<SelectionUniforms>) {
+    let mut pass = match graphics.render() {
+        Ok(pass) => pass,
+        Err(err) => {
+            eprintln!("Error rendering frame: {:?}", err);
+            return;
+        }
+    };
+    bundle.draw(&mut pass);
+    pass.finish();
+}
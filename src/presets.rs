@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{capture::WindowInfo, export::Rect};
+
+/// A named region, kept in `<config dir>/cleave/presets.toml` as a table per preset.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Preset {
+    #[serde(flatten)]
+    pub rect: Rect,
+    /// Monitor to capture, the primary one if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor_id: Option<u32>,
+    /// Part of a window title, `rect` is then relative to the top left corner of that window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<String>,
+}
+
+impl Preset {
+    /// Where the preset is on a capture with these windows.
+    pub fn resolve(&self, windows: &[WindowInfo]) -> anyhow::Result<Rect> {
+        let Some(title) = &self.window else {
+            return Ok(self.rect);
+        };
+        let window = windows
+            .iter()
+            .find(|w| w.title.contains(title.as_str()))
+            .with_context(|| format!("No window titled {:?} is open", title))?;
+        Ok(Rect {
+            x: window.x.saturating_add_unsigned(self.rect.x).max(0) as u32,
+            y: window.y.saturating_add_unsigned(self.rect.y).max(0) as u32,
+            ..self.rect
+        })
+    }
+
+    /// Whether the preset can be selected on a capture of this monitor.
+    pub fn on_monitor(&self, monitor_id: u32) -> bool {
+        self.monitor_id.is_none_or(|id| id == monitor_id)
+    }
+
+    /// Makes `rect` relative to the smallest window containing its centre, if any.
+    pub fn relative_to_window(rect: Rect, windows: &[WindowInfo]) -> Self {
+        let (cx, cy) = (
+            (rect.x + rect.width / 2) as i32,
+            (rect.y + rect.height / 2) as i32,
+        );
+        let window = windows
+            .iter()
+            .filter(|w| {
+                (w.x..w.x + w.width as i32).contains(&cx)
+                    && (w.y..w.y + w.height as i32).contains(&cy)
+            })
+            .min_by_key(|w| w.width as u64 * w.height as u64);
+        let Some(window) = window else {
+            return Self {
+                rect,
+                monitor_id: None,
+                window: None,
+            };
+        };
+        Self {
+            rect: Rect {
+                x: (rect.x as i32 - window.x).max(0) as u32,
+                y: (rect.y as i32 - window.y).max(0) as u32,
+                ..rect
+            },
+            monitor_id: None,
+            window: Some(window.title.clone()),
+        }
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("cleave").join("presets.toml"))
+}
+
+/// Every preset by name, sorted.
+pub fn load() -> anyhow::Result<BTreeMap<String, Preset>> {
+    let Some(path) = path().filter(|p| p.exists()) else {
+        return Ok(BTreeMap::new());
+    };
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read presets at {}", path.display()))?;
+    toml::from_str(&contents)
+        .with_context(|| format!("Could not parse presets at {}", path.display()))
+}
+
+pub fn get(name: &str) -> anyhow::Result<Preset> {
+    load()?
+        .remove(name)
+        .with_context(|| format!("There is no preset named {:?}", name))
+}
+
+/// The presets listed on the overlay to choose one from.
+pub struct Picker {
+    presets: Vec<(String, Preset)>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(presets: BTreeMap<String, Preset>) -> anyhow::Result<Self> {
+        if presets.is_empty() {
+            anyhow::bail!("There are no presets yet, save one with --save-preset");
+        }
+        Ok(Self {
+            presets: presets.into_iter().collect(),
+            selected: 0,
+        })
+    }
+
+    /// Moves the highlight by `delta` entries, wrapping around at either end.
+    pub fn move_by(&mut self, delta: isize) {
+        let len = self.presets.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// The preset at `index`, or the highlighted one.
+    pub fn get(&self, index: Option<usize>) -> Option<&(String, Preset)> {
+        self.presets.get(index.unwrap_or(self.selected))
+    }
+
+    /// The list as shown on the overlay, numbering the first nine entries and marking the
+    /// highlighted one.
+    pub fn lines(&self, monitor_id: u32) -> Vec<String> {
+        let mut lines = vec!["Presets: Up/Down and Enter, or 1-9".to_string()];
+        for (i, (name, preset)) in self.presets.iter().enumerate() {
+            let marker = if i == self.selected { '>' } else { ' ' };
+            let number = if i < 9 { (b'1' + i as u8) as char } else { ' ' };
+            let mut line = format!("{} {} {}", marker, number, name);
+            if let Some(window) = &preset.window {
+                line.push_str(&format!(" (in {})", window));
+            }
+            if !preset.on_monitor(monitor_id) {
+                line.push_str(" - other monitor");
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+/// Adds a preset, replacing any with the same name.
+pub fn save(name: &str, preset: Preset) -> anyhow::Result<()> {
+    let path = path().context("Could not find a config directory for the presets")?;
+    let mut presets = load()?;
+    presets.insert(name.to_string(), preset);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, toml::to_string(&presets)?)
+        .with_context(|| format!("Could not write presets to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(monitor_id: Option<u32>, window: Option<&str>) -> Preset {
+        Preset {
+            rect: Rect {
+                x: 10,
+                y: 20,
+                width: 30,
+                height: 40,
+            },
+            monitor_id,
+            window: window.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn lists_presets_to_pick_from() {
+        let presets = BTreeMap::from([
+            ("sidebar".to_string(), preset(Some(1), None)),
+            ("chart".to_string(), preset(None, Some("Grafana"))),
+            ("laptop".to_string(), preset(Some(2), None)),
+        ]);
+        let mut picker = Picker::new(presets).unwrap();
+        assert_eq!(
+            picker.lines(1)[1..],
+            [
+                "> 1 chart (in Grafana)",
+                "  2 laptop - other monitor",
+                "  3 sidebar",
+            ]
+        );
+        picker.move_by(-1);
+        assert_eq!(picker.get(None).unwrap().0, "sidebar");
+        picker.move_by(2);
+        assert_eq!(picker.get(None).unwrap().0, "laptop");
+        assert_eq!(picker.get(Some(0)).unwrap().0, "chart");
+        assert!(picker.get(Some(3)).is_none());
+    }
+
+    #[test]
+    fn needs_presets_to_pick_from() {
+        assert!(Picker::new(BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn resolves_window_presets() {
+        let window = WindowInfo {
+            title: "Grafana - Dashboards".to_string(),
+            app_name: "firefox".to_string(),
+            x: 100,
+            y: 50,
+            width: 800,
+            height: 600,
+            minimized: false,
+        };
+        let rect = preset(None, Some("Grafana")).resolve(&[window]).unwrap();
+        assert_eq!((rect.x, rect.y), (110, 70));
+        assert!(preset(None, Some("Slack")).resolve(&[]).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use image::{Rgba, RgbaImage};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowAttributes, WindowId, WindowLevel},
+};
+
+use cleave_graphics::prelude::*;
+
+use crate::{
+    animation::{self, Format},
+    capture::{Recorder, Source},
+    config::RecordConfig,
+    context::SelectionUniforms,
+    export::Rect,
+    pin,
+};
+
+const INDICATOR_SIZE: u32 = 32;
+// Recordings stop early once the frames take up this much memory
+const MAX_BYTES: usize = 1 << 30;
+
+/// Captures `rect` at the configured frame rate until `stop` is set or the time is up, then
+/// saves the frames as an animation, each shown for as long as it was on screen. Returns where
+/// it was saved.
+pub fn record(
+    source: &Source,
+    monitor_id: u32,
+    rect: Rect,
+    config: &RecordConfig,
+    output: Option<&Path>,
+    stop: &AtomicBool,
+) -> anyhow::Result<PathBuf> {
+    if output == Some(Path::new("-")) {
+        anyhow::bail!("Recordings can't be written to stdout");
+    }
+    let recorder = Recorder::new(source, Some(monitor_id))?;
+    let interval = Duration::from_secs(1) / config.fps.clamp(1, 100);
+    let started = Instant::now();
+    let mut next = started;
+    let mut frames: Vec<RgbaImage> = Vec::new();
+    let mut shown = Vec::new();
+    let mut bytes = 0;
+    while !stop.load(Ordering::Relaxed) && started.elapsed().as_secs() < config.max_seconds {
+        let frame = match recorder.frame(rect) {
+            Ok(frame) => frame,
+            Err(err) if frames.is_empty() => return Err(err),
+            Err(err) => {
+                eprintln!("Error capturing frame, stopping the recording: {:?}", err);
+                break;
+            }
+        };
+        // Unchanged frames only extend how long the previous one is shown
+        if frames.last() != Some(&frame) {
+            bytes += frame.as_raw().len();
+            if bytes > MAX_BYTES {
+                eprintln!("Stopping the recording, it is using too much memory");
+                break;
+            }
+            frames.push(frame);
+            shown.push(Instant::now());
+        }
+        next += interval;
+        std::thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+    shown.push(Instant::now());
+    let delays: Vec<_> = shown.windows(2).map(|pair| pair[1] - pair[0]).collect();
+
+    let format = output.and_then(Format::from_path).unwrap_or(config.format);
+    let path = match output {
+        Some(path) => path.to_path_buf(),
+        None => default_path(format)?,
+    };
+    animation::save(&frames, &delays, format, &path)?;
+    Ok(path)
+}
+
+/// `cleave-<timestamp>` in the videos or pictures directory.
+fn default_path(format: Format) -> anyhow::Result<PathBuf> {
+    let dir = dirs::video_dir()
+        .or_else(dirs::picture_dir)
+        .or_else(dirs::home_dir)
+        .context("Could not find a directory to save the recording to")?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    Ok(dir.join(format!("cleave-{}.{}", timestamp, format.extension())))
+}
+
+/// A small red dot shown while recording, clicking it stops the recording.
+pub struct Indicator {
+    graphics: Graphics<Window>,
+    bundle: GraphicsBundle<SelectionUniforms>,
+    pub stop: Arc<AtomicBool>,
+}
+
+impl Indicator {
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        position: PhysicalPosition<i32>,
+    ) -> anyhow::Result<Self> {
+        let size = PhysicalSize::new(INDICATOR_SIZE, INDICATOR_SIZE);
+        let window = event_loop.create_window(
+            WindowAttributes::default()
+                .with_inner_size(size)
+                .with_position(position)
+                .with_title("Cleave Recording")
+                .with_decorations(false)
+                .with_resizable(false)
+                .with_window_level(WindowLevel::AlwaysOnTop),
+        )?;
+        let graphics = Graphics::new(window, size.width, size.height);
+        let graphics = pollster::block_on(graphics)?;
+        let radius = INDICATOR_SIZE as f32 / 2.0;
+        let dot = RgbaImage::from_fn(size.width, size.height, |x, y| {
+            let (dx, dy) = (x as f32 + 0.5 - radius, y as f32 + 0.5 - radius);
+            if (dx * dx + dy * dy).sqrt() < radius * 0.7 {
+                Rgba([220, 40, 40, 255])
+            } else {
+                Rgba([32, 32, 36, 255])
+            }
+        });
+        let bundle = GraphicsBundle::new(
+            dot.into(),
+            &graphics.device,
+            &graphics.queue,
+            wgpu::PrimitiveTopology::TriangleStrip,
+            graphics.config.format,
+        );
+        graphics.request_redraw();
+        Ok(Self {
+            graphics,
+            bundle,
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.graphics.id()
+    }
+
+    /// Returns `false` once the recording should stop.
+    pub fn handle_event(&mut self, event: WindowEvent) -> bool {
+        match event {
+            WindowEvent::RedrawRequested => pin::draw(&mut self.graphics, &self.bundle),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            }
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Escape),
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::CloseRequested => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// Tells the recording thread to finish.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
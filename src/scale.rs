@@ -0,0 +1,123 @@
+use clap::ValueEnum;
+use image::{imageops, RgbaImage};
+
+/// How pixels are resampled when scaling.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Filter {
+    /// Keeps hard pixel edges, best for pixel art and whole-number factors
+    Nearest,
+    Bilinear,
+    /// Sharpest, but slowest
+    #[default]
+    Lanczos,
+}
+
+impl From<Filter> for imageops::FilterType {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => imageops::FilterType::Nearest,
+            Filter::Bilinear => imageops::FilterType::Triangle,
+            Filter::Lanczos => imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How exported images are resized.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Scaling {
+    pub factor: Option<f32>,
+    pub max_size: Option<u32>, // Of the longer side, images are never enlarged to fit
+    pub filter: Filter,
+    pub logical: bool, // Undo the monitor's scale factor, so 2x captures come out at 1x
+}
+
+impl Scaling {
+    /// Resizes `image`, which was captured on a monitor with the given scale factor.
+    pub fn apply(&self, image: RgbaImage, scale_factor: f32) -> RgbaImage {
+        let mut factor = self.factor.unwrap_or(1.0);
+        if self.logical && scale_factor > 0.0 {
+            factor /= scale_factor;
+        }
+        let (width, height) = image.dimensions();
+        if let Some(max_size) = self.max_size {
+            let longest = width.max(height) as f32 * factor;
+            if longest > max_size as f32 {
+                factor *= max_size as f32 / longest;
+            }
+        }
+        if factor == 1.0 {
+            return image;
+        }
+        let width = (width as f32 * factor).round().max(1.0) as u32;
+        let height = (height as f32 * factor).round().max(1.0) as u32;
+        imageops::resize(&image, width, height, self.filter.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn image(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([10, 20, 30, 255]))
+    }
+
+    fn scaled(scaling: Scaling, width: u32, height: u32, scale_factor: f32) -> (u32, u32) {
+        scaling
+            .apply(image(width, height), scale_factor)
+            .dimensions()
+    }
+
+    #[test]
+    fn multiplies_the_factors() {
+        let logical = Scaling {
+            logical: true,
+            ..Scaling::default()
+        };
+        assert_eq!(scaled(logical, 400, 200, 2.0), (200, 100));
+        let scaled_up = Scaling {
+            factor: Some(1.5),
+            ..logical
+        };
+        assert_eq!(scaled(scaled_up, 400, 200, 2.0), (300, 150));
+        let limited = Scaling {
+            max_size: Some(240),
+            ..scaled_up
+        };
+        assert_eq!(scaled(limited, 400, 200, 2.0), (240, 120));
+        // Without logical sizes the monitor's scale factor is ignored
+        assert_eq!(scaled(Scaling::default(), 400, 200, 2.0), (400, 200));
+    }
+
+    #[test]
+    fn never_enlarges_to_fit() {
+        let limited = Scaling {
+            max_size: Some(1000),
+            ..Scaling::default()
+        };
+        assert_eq!(scaled(limited, 400, 200, 1.0), (400, 200));
+        let logical = Scaling {
+            logical: true,
+            ..limited
+        };
+        assert_eq!(scaled(logical, 400, 200, 0.5), (800, 400));
+        assert_eq!(scaled(logical, 1600, 400, 0.5), (1000, 250));
+    }
+
+    #[test]
+    fn keeps_at_least_one_pixel() {
+        let tiny = Scaling {
+            factor: Some(0.001),
+            ..Scaling::default()
+        };
+        assert_eq!(scaled(tiny, 10, 10, 1.0), (1, 1));
+        let limited = Scaling {
+            max_size: Some(10),
+            ..Scaling::default()
+        };
+        assert_eq!(scaled(limited, 1000, 2, 1.0), (10, 1));
+        assert_eq!(scaled(limited, 1, 1, 1.0), (1, 1));
+    }
+}
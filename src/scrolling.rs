@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+
+use crate::{
+    capture::{Recorder, Source},
+    export::Rect,
+    stitch::Stitcher,
+};
+
+const INTERVAL: Duration = Duration::from_millis(150);
+const START_TIMEOUT: Duration = Duration::from_secs(10); // To start scrolling
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2); // Once scrolling stops
+const MAX_HEIGHT: u32 = 32_000;
+
+/// Captures `rect` over and over while the user scrolls down, and stitches the frames into
+/// one tall image. Ends once nothing new has scrolled into view for a while.
+pub fn capture(source: &Source, monitor_id: u32, rect: Rect) -> anyhow::Result<RgbaImage> {
+    let recorder = Recorder::new(source, Some(monitor_id))?;
+    // Give the overlay time to disappear
+    std::thread::sleep(INTERVAL);
+    let mut stitcher = Stitcher::new(recorder.frame(rect)?);
+    let mut last_change = Instant::now();
+    let mut scrolled = false;
+    loop {
+        std::thread::sleep(INTERVAL);
+        if stitcher.push(recorder.frame(rect)?)? {
+            last_change = Instant::now();
+            scrolled = true;
+        }
+        let timeout = if scrolled {
+            IDLE_TIMEOUT
+        } else {
+            START_TIMEOUT
+        };
+        if last_change.elapsed() > timeout || stitcher.height() >= MAX_HEIGHT {
+            return Ok(stitcher.finish());
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use glam::Vec2;
+
+use crate::export::{Mask, Rect, Region};
+
+/// The outline of a selection. A lasso keeps its points in pixels of the captured image.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum Shape {
+    #[default]
+    Rect,
+    Ellipse,
+    Lasso(Vec<Vec2>),
+}
+
+impl Shape {
+    /// Identifies the shape in the shader
+    pub fn id(&self) -> u32 {
+        match self {
+            Shape::Rect => 0,
+            Shape::Ellipse => 1,
+            Shape::Lasso(_) => 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drag {
+    pub start: Vec2,
+    pub end: Option<Vec2>,
+    pub additive: bool, // Keep the current selection as a region instead of replacing it
+    pub shape: Shape,
+}
+
+impl Drag {
+    /// Turns the drag into a selection, `None` if a lasso has too few points to enclose anything.
+    pub fn finish(self) -> Option<Selection> {
+        let end = self.end.unwrap_or(self.start); // Use end if set, otherwise use start
+        let (start, end) = match &self.shape {
+            Shape::Lasso(points) if points.len() < 3 => return None,
+            Shape::Lasso(points) => bounds(points),
+            _ => (self.start, end),
+        };
+        Some(Selection {
+            start,
+            end,
+            shape: self.shape,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub shape: Shape,
+}
+
+impl Selection {
+    pub fn rect(&self) -> Rect {
+        let (min, max) = (self.start.min(self.end), self.start.max(self.end));
+        let (min, max) = (min.ceil(), max.floor());
+        Rect {
+            x: min.x as u32,
+            y: min.y as u32,
+            width: (max.x - min.x).max(0.0) as u32,
+            height: (max.y - min.y).max(0.0) as u32,
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        let rect = self.rect();
+        let mask = match &self.shape {
+            Shape::Rect => Mask::None,
+            Shape::Ellipse => Mask::Ellipse,
+            Shape::Lasso(points) => {
+                let origin = Vec2::new(rect.x as f32, rect.y as f32);
+                Mask::Polygon(points.iter().map(|p| *p - origin).collect())
+            }
+        };
+        Region { rect, mask }
+    }
+
+    /// Moves the corners of the selection, stretching a lasso to fit the new bounds.
+    pub fn set_bounds(&mut self, start: Vec2, end: Vec2) {
+        if let Shape::Lasso(points) = &mut self.shape {
+            let (old_min, old_max) = (self.start.min(self.end), self.start.max(self.end));
+            let (new_min, new_max) = (start.min(end), start.max(end));
+            let scale = (new_max - new_min) / (old_max - old_min).max(Vec2::ONE);
+            for point in points {
+                *point = new_min + (*point - old_min) * scale;
+            }
+        }
+        self.start = start;
+        self.end = end;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserSelection {
+    pub drag: Option<Drag>,
+    pub selection: Option<Selection>,
+    pub regions: Vec<Selection>, // Earlier selections kept by additive drags
+}
+
+impl UserSelection {
+    pub fn new() -> Self {
+        Self {
+            drag: None,
+            selection: None,
+            regions: Vec::new(),
+        }
+    }
+
+    /// The state without any drag in progress, which is what undo and redo restore.
+    pub fn committed(&self) -> Self {
+        Self {
+            drag: None,
+            ..self.clone()
+        }
+    }
+
+    /// Every selected region, oldest first, ending with the active selection.
+    pub fn regions(&self) -> Vec<Region> {
+        self.regions
+            .iter()
+            .chain(&self.selection)
+            .map(Selection::region)
+            .collect()
+    }
+}
+
+fn bounds(points: &[Vec2]) -> (Vec2, Vec2) {
+    let min = points.iter().copied().reduce(Vec2::min).unwrap_or_default();
+    let max = points.iter().copied().reduce(Vec2::max).unwrap_or_default();
+    (min, max)
+}
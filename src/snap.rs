@@ -0,0 +1,125 @@
+use glam::Vec2;
+use image::{Rgba, RgbaImage};
+
+// Minimum luma difference between neighbouring pixels to count as an edge
+const EDGE_CONTRAST: i16 = 32;
+// Minimum number of consecutive edge pixels for a line to become a snap target,
+// this keeps text and noise from producing targets everywhere
+const MIN_EDGE_RUN: u32 = 24;
+
+/// Horizontal and vertical lines that the selection edges can snap to,
+/// in the same screen coordinates as the selection.
+#[derive(Default, Debug)]
+pub struct SnapTargets {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+}
+
+fn luma(pixel: &Rgba<u8>) -> i16 {
+    let [r, g, b, _] = pixel.0;
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as i16
+}
+
+impl SnapTargets {
+    /// Detects strong horizontal and vertical edges in the captured image.
+    pub fn detect(img: &RgbaImage) -> Self {
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let luma: Vec<i16> = img.pixels().map(luma).collect();
+        let mut targets = Self::default();
+        if width == 0 || height == 0 {
+            return targets;
+        }
+        targets.add_rect(Vec2::ZERO, Vec2::new(width as f32, height as f32));
+
+        // Vertical edges lie between column x - 1 and x
+        let mut runs = vec![0u32; width];
+        let mut longest = vec![0u32; width];
+        for row in luma.chunks_exact(width) {
+            for x in 1..width {
+                if (row[x] - row[x - 1]).abs() >= EDGE_CONTRAST {
+                    runs[x] += 1;
+                    longest[x] = longest[x].max(runs[x]);
+                } else {
+                    runs[x] = 0;
+                }
+            }
+        }
+        targets.xs.extend(
+            (1..width)
+                .filter(|&x| longest[x] >= MIN_EDGE_RUN)
+                .map(|x| x as f32),
+        );
+
+        // Horizontal edges lie between row y - 1 and y
+        for y in 1..height {
+            let (above, row) = (&luma[(y - 1) * width..y * width], &luma[y * width..]);
+            let (mut run, mut longest) = (0, 0);
+            for x in 0..width {
+                if (row[x] - above[x]).abs() >= EDGE_CONTRAST {
+                    run += 1;
+                    longest = longest.max(run);
+                } else {
+                    run = 0;
+                }
+            }
+            if longest >= MIN_EDGE_RUN {
+                targets.ys.push(y as f32);
+            }
+        }
+
+        targets
+    }
+
+    /// Adds the borders of a rectangle, e.g. a window, as snap targets.
+    pub fn add_rect(&mut self, min: Vec2, max: Vec2) {
+        self.xs.extend([min.x, max.x]);
+        self.ys.extend([min.y, max.y]);
+    }
+
+    /// Moves each axis of `pos` onto the nearest target within `distance`.
+    pub fn snap(&self, pos: Vec2, distance: f32) -> Vec2 {
+        Vec2::new(
+            nearest(&self.xs, pos.x, distance),
+            nearest(&self.ys, pos.y, distance),
+        )
+    }
+}
+
+fn nearest(targets: &[f32], value: f32, distance: f32) -> f32 {
+    targets
+        .iter()
+        .copied()
+        .filter(|t| (t - value).abs() <= distance)
+        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_image_has_no_targets() {
+        let targets = SnapTargets::detect(&RgbaImage::new(0, 0));
+        assert!(targets.xs.is_empty() && targets.ys.is_empty());
+    }
+
+    #[test]
+    fn detects_box_edges() {
+        let mut img = RgbaImage::from_pixel(100, 100, Rgba([255, 255, 255, 255]));
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            if (20..60).contains(&x) && (30..80).contains(&y) {
+                *pixel = Rgba([0, 0, 0, 255]);
+            }
+        }
+        let targets = SnapTargets::detect(&img);
+        assert_eq!(
+            targets.snap(Vec2::new(22.0, 77.0), 4.0),
+            Vec2::new(20.0, 80.0)
+        );
+        assert_eq!(
+            targets.snap(Vec2::new(50.0, 50.0), 4.0),
+            Vec2::new(50.0, 50.0)
+        );
+    }
+}
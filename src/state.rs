@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::export::Rect;
+
+/// The region that was captured last, kept in `<state dir>/cleave/last.json` to repeat it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LastRegion {
+    pub monitor: String,
+    pub monitor_id: u32,
+    pub rect: Rect,
+}
+
+impl LastRegion {
+    pub fn path() -> Option<PathBuf> {
+        // Only Linux has a state directory
+        let dir = dirs::state_dir().or_else(dirs::data_dir)?;
+        Some(dir.join("cleave").join("last.json"))
+    }
+
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let Some(path) = Self::path().filter(|p| p.exists()) else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let last = serde_json::from_str(&contents)
+            .with_context(|| format!("Could not parse {}", path.display()))?;
+        Ok(Some(last))
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().context("Could not find a directory to keep the last region")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+}
@@ -0,0 +1,176 @@
+use image::RgbaImage;
+
+// Consecutive frames have to share at least this many rows to be joined
+const MIN_OVERLAP: usize = 8;
+// Share of rows that may differ in the overlap, e.g. a blinking caret or a sticky header
+const MAX_MISMATCH: f32 = 0.1;
+// Consecutive frames that may fail to line up, e.g. while a tooltip is shown, before the
+// content is considered lost because it scrolled by more than a whole frame
+const MAX_MISSES: u32 = 3;
+
+/// A row of pixels, reduced to what is needed to compare it with others.
+#[derive(Clone, Copy, PartialEq)]
+struct Row {
+    hash: u64,
+    uniform: bool, // Every pixel is the same, so it matches any other such row
+}
+
+fn rows(image: &RgbaImage) -> Vec<Row> {
+    image
+        .rows()
+        .map(|row| {
+            let mut hash: u64 = 0xcbf29ce484222325; // FNV-1a
+            let mut first = None;
+            let mut uniform = true;
+            for pixel in row {
+                for byte in pixel.0 {
+                    hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+                }
+                uniform &= *first.get_or_insert(pixel) == pixel;
+            }
+            Row { hash, uniform }
+        })
+        .collect()
+}
+
+/// How many rows at the bottom of `previous` show up again at the top of `next`, as when
+/// scrolling down. `None` if the frames don't line up at all.
+///
+/// The largest overlap wins, so identical frames overlap completely. Uniform rows, such as
+/// blank space, are ignored since they would line up with each other anywhere.
+pub fn find_overlap(previous: &RgbaImage, next: &RgbaImage) -> Option<u32> {
+    if previous.width() != next.width() {
+        return None;
+    }
+    let (above, below) = (rows(previous), rows(next));
+    let height = above.len().min(below.len());
+    (MIN_OVERLAP..=height)
+        .rev()
+        .find(|&overlap| {
+            let (mut informative, mut mismatches) = (0, 0);
+            for (a, b) in above[above.len() - overlap..].iter().zip(&below[..overlap]) {
+                if a.uniform && b.uniform && a == b {
+                    continue;
+                }
+                informative += 1;
+                if a != b {
+                    mismatches += 1;
+                }
+            }
+            informative > 0 && mismatches as f32 <= informative as f32 * MAX_MISMATCH
+        })
+        .map(|overlap| overlap as u32)
+}
+
+/// Joins frames of a region that is scrolled down into one tall image.
+pub struct Stitcher {
+    image: RgbaImage,
+    last: RgbaImage,
+    misses: u32,
+}
+
+impl Stitcher {
+    pub fn new(first: RgbaImage) -> Self {
+        Self {
+            image: first.clone(),
+            last: first,
+            misses: 0,
+        }
+    }
+
+    /// Appends whatever scrolled into view in `frame`, returns whether anything was added.
+    /// A few frames that don't line up with the previous one are skipped, after that the
+    /// content has moved on and joining more frames would leave a gap, which is an error.
+    pub fn push(&mut self, frame: RgbaImage) -> anyhow::Result<bool> {
+        let Some(overlap) = find_overlap(&self.last, &frame) else {
+            self.misses += 1;
+            if self.misses >= MAX_MISSES {
+                anyhow::bail!("Lost track of the content, try scrolling more slowly");
+            }
+            return Ok(false);
+        };
+        self.misses = 0;
+        let added = frame.height() - overlap;
+        if added == 0 {
+            return Ok(false);
+        }
+        let (width, height) = self.image.dimensions();
+        let mut image = RgbaImage::new(width, height + added);
+        image::imageops::replace(&mut image, &self.image, 0, 0);
+        let new_rows = image::imageops::crop_imm(&frame, 0, overlap, width, added).to_image();
+        image::imageops::replace(&mut image, &new_rows, 0, height as i64);
+        self.image = image;
+        self.last = frame;
+        Ok(true)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    pub fn finish(self) -> RgbaImage {
+        self.image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// A page with different content on every row, plus some blank rows.
+    fn page(height: u32) -> RgbaImage {
+        RgbaImage::from_fn(40, height, |x, y| {
+            if y % 50 < 5 {
+                return Rgba([255, 255, 255, 255]);
+            }
+            let noise = (x * 7919 + y * 104729) ^ (y * y * 31);
+            Rgba([noise as u8, (noise >> 8) as u8, y as u8, 255])
+        })
+    }
+
+    fn slice(image: &RgbaImage, y: u32, height: u32) -> RgbaImage {
+        image::imageops::crop_imm(image, 0, y, image.width(), height).to_image()
+    }
+
+    #[test]
+    fn finds_the_overlap_of_shifted_frames() {
+        let page = page(300);
+        assert_eq!(
+            find_overlap(&slice(&page, 0, 100), &slice(&page, 30, 100)),
+            Some(70)
+        );
+        assert_eq!(
+            find_overlap(&slice(&page, 0, 100), &slice(&page, 0, 100)),
+            Some(100)
+        );
+        assert_eq!(
+            find_overlap(&slice(&page, 0, 100), &slice(&page, 150, 100)),
+            None
+        );
+    }
+
+    #[test]
+    fn stitches_scrolled_frames() {
+        let page = page(400);
+        let mut stitcher = Stitcher::new(slice(&page, 0, 100));
+        for y in [30, 30, 90, 175, 260] {
+            stitcher.push(slice(&page, y, 100)).unwrap();
+        }
+        assert_eq!(stitcher.finish(), slice(&page, 0, 360));
+    }
+
+    #[test]
+    fn fails_once_frames_stop_lining_up() {
+        let page = page(400);
+        let mut stitcher = Stitcher::new(slice(&page, 0, 100));
+        // A single frame that doesn't fit is skipped
+        assert!(!stitcher.push(slice(&page, 250, 100)).unwrap());
+        assert!(stitcher.push(slice(&page, 50, 100)).unwrap());
+        for _ in 1..MAX_MISSES {
+            assert!(!stitcher.push(slice(&page, 300, 100)).unwrap());
+        }
+        assert!(stitcher.push(slice(&page, 300, 100)).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use image::RgbaImage;
+
+use crate::{
+    animation, config::RecordConfig, export, headless::Shot, metadata::Metadata, scale::Scaling,
+};
+
+// Set once Ctrl+C is pressed, so the frames taken so far can still be assembled
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// How often and for how long to capture.
+pub struct Timelapse {
+    pub interval: Duration,
+    pub duration: Option<Duration>, // Until interrupted if not set
+    pub animate: bool,              // Also join the frames into an animation
+    pub scaling: Scaling,
+    pub metadata: bool, // Embed where each frame came from
+}
+
+impl Timelapse {
+    /// Takes a shot with `capture` at every interval, usually of a preset or the last region,
+    /// writing `frame-00001.png` and so on into `dir`. Returns the animation if one was made,
+    /// or otherwise `dir`.
+    pub fn run(
+        &self,
+        mut capture: impl FnMut() -> anyhow::Result<Shot>,
+        dir: &Path,
+        config: &RecordConfig,
+    ) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create {}", dir.display()))?;
+        let _interrupt = CatchInterrupt::new();
+
+        let started = Instant::now();
+        let mut frames = Vec::new();
+        let mut next = started;
+        loop {
+            // A dashboard that is briefly gone shouldn't end a long timelapse, but a region
+            // that can't be captured at all should
+            match capture() {
+                Ok(shot) => {
+                    let path = dir.join(format!("frame-{:05}.png", frames.len() + 1));
+                    let metadata = self.metadata.then(|| {
+                        Metadata::new(
+                            &shot.monitor,
+                            Some(shot.rect),
+                            shot.scale_factor,
+                            shot.captured_at,
+                        )
+                    });
+                    let image = self.scaling.apply(shot.image, shot.scale_factor);
+                    export::save(&image, &path, metadata.as_ref())?;
+                    eprintln!("Saved {}", path.display());
+                    frames.push(path);
+                }
+                Err(err) if frames.is_empty() => return Err(err),
+                Err(err) => eprintln!("Could not capture frame: {:?}", err),
+            }
+            next += self.interval;
+            if self
+                .duration
+                .is_some_and(|d| next.duration_since(started) > d)
+            {
+                break;
+            }
+            if !sleep_until(next) {
+                break;
+            }
+        }
+
+        if !self.animate {
+            return Ok(dir.to_path_buf());
+        }
+        let images = frames
+            .iter()
+            .map(|path| Ok(image::open(path)?.to_rgba8()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let path = dir.join(format!("timelapse.{}", config.format.extension()));
+        let delays = vec![Duration::from_secs(1) / config.fps.clamp(1, 100); images.len()];
+        animation::save(&same_size(images), &delays, config.format, &path)?;
+        Ok(path)
+    }
+}
+
+/// Sleeps until `deadline`, returns `false` if interrupted first.
+fn sleep_until(deadline: Instant) -> bool {
+    while Instant::now() < deadline {
+        if INTERRUPTED.load(Ordering::Relaxed) {
+            return false;
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        std::thread::sleep(left.min(Duration::from_millis(100)));
+    }
+    !INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Makes Ctrl+C end the timelapse instead of killing the process, until dropped.
+struct CatchInterrupt {
+    #[cfg(unix)]
+    previous: libc::sighandler_t,
+}
+
+impl CatchInterrupt {
+    #[cfg(unix)]
+    fn new() -> Self {
+        extern "C" fn interrupt(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::Relaxed);
+        }
+        INTERRUPTED.store(false, Ordering::Relaxed);
+        // Storing to an atomic is all the handler does, which is safe in a signal handler
+        let previous = unsafe {
+            libc::signal(
+                libc::SIGINT,
+                interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            )
+        };
+        Self { previous }
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Self {
+        Self {}
+    }
+}
+
+#[cfg(unix)]
+impl Drop for CatchInterrupt {
+    fn drop(&mut self) {
+        if self.previous != libc::SIG_ERR {
+            unsafe { libc::signal(libc::SIGINT, self.previous) };
+        }
+    }
+}
+
+/// Pads or crops every frame to the size of the first, regions near the edge of the
+/// screen can come out smaller when a window moves.
+fn same_size(frames: Vec<RgbaImage>) -> Vec<RgbaImage> {
+    let Some((width, height)) = frames.first().map(RgbaImage::dimensions) else {
+        return frames;
+    };
+    frames
+        .into_iter()
+        .map(|frame| {
+            if frame.dimensions() == (width, height) {
+                return frame;
+            }
+            let mut canvas = RgbaImage::new(width, height);
+            image::imageops::replace(&mut canvas, &frame, 0, 0);
+            canvas
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        capture::{Capture, Source},
+        export::Rect,
+        metadata,
+    };
+
+    use super::*;
+
+    #[test]
+    fn writes_numbered_frames() {
+        let dir = std::env::temp_dir().join(format!("cleave-timelapse-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let timelapse = Timelapse {
+            interval: Duration::from_millis(100),
+            duration: Some(Duration::from_millis(250)),
+            animate: true,
+            scaling: Scaling::default(),
+            metadata: true,
+        };
+        let rect = Rect {
+            x: 100,
+            y: 50,
+            width: 64,
+            height: 48,
+        };
+        let capture = || Shot::new(Capture::of_output(&Source::Mock, None)?, rect);
+        let path = timelapse
+            .run(capture, &dir, &RecordConfig::default())
+            .unwrap();
+        assert_eq!(path, dir.join("timelapse.gif"));
+
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|f| f.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        let frames = ["frame-00001.png", "frame-00002.png", "frame-00003.png"];
+        assert_eq!(files, [&frames[..], &["timelapse.gif"]].concat());
+        let frame = dir.join(frames[0]);
+        assert_eq!(
+            image::open(&frame).unwrap().to_rgba8().dimensions(),
+            (64, 48)
+        );
+        let chunks = metadata::read(&frame).unwrap();
+        assert!(chunks.contains(&("Source".to_string(), "mock".to_string())));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
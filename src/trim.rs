@@ -0,0 +1,100 @@
+use image::{Rgba, RgbaImage};
+
+use crate::export::Rect;
+
+/// Shrinks `rect` until every side touches something other than the background, which is the
+/// colour of its top left pixel. Channels may differ by up to `tolerance` and still count as
+/// background. Returns `None` if there is nothing but background.
+pub fn trim(image: &RgbaImage, rect: Rect, tolerance: u8) -> Option<Rect> {
+    let right = rect.right().min(image.width());
+    let bottom = rect.bottom().min(image.height());
+    if rect.x >= right || rect.y >= bottom {
+        return None;
+    }
+    let background = *image.get_pixel(rect.x, rect.y);
+    let is_background = |x: u32, y: u32| similar(image.get_pixel(x, y), &background, tolerance);
+    let row_is_background = |y: u32| (rect.x..right).all(|x| is_background(x, y));
+
+    let top = (rect.y..bottom).find(|&y| !row_is_background(y))?;
+    let bottom = (top..bottom).rfind(|&y| !row_is_background(y))? + 1;
+    let column_is_background = |x: u32| (top..bottom).all(|y| is_background(x, y));
+    let left = (rect.x..right).find(|&x| !column_is_background(x))?;
+    let right = (left..right).rfind(|&x| !column_is_background(x))? + 1;
+    Some(Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+fn similar(a: &Rgba<u8>, b: &Rgba<u8>, tolerance: u8) -> bool {
+    a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: Rgba<u8> = Rgba([100, 100, 100, 255]);
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A plain image with `content` filled in.
+    fn image(content: Rect, color: Rgba<u8>) -> RgbaImage {
+        RgbaImage::from_fn(40, 30, |x, y| {
+            let inside = (content.x..content.right()).contains(&x)
+                && (content.y..content.bottom()).contains(&y);
+            if inside {
+                color
+            } else {
+                BACKGROUND
+            }
+        })
+    }
+
+    #[test]
+    fn finds_nothing_on_plain_background() {
+        let plain = RgbaImage::from_pixel(40, 30, BACKGROUND);
+        assert_eq!(trim(&plain, rect(0, 0, 40, 30), 0), None);
+        assert_eq!(trim(&plain, rect(40, 0, 10, 10), 0), None);
+    }
+
+    #[test]
+    fn shrinks_to_the_content() {
+        let image = image(rect(10, 5, 6, 4), Rgba([0, 0, 0, 255]));
+        assert_eq!(trim(&image, rect(0, 0, 40, 30), 0), Some(rect(10, 5, 6, 4)));
+        // Content outside the selection is ignored
+        assert_eq!(trim(&image, rect(2, 2, 10, 10), 0), Some(rect(10, 5, 2, 4)));
+    }
+
+    #[test]
+    fn keeps_content_touching_an_edge() {
+        let image = image(rect(30, 10, 10, 5), Rgba([0, 0, 0, 255]));
+        assert_eq!(
+            trim(&image, rect(0, 0, 40, 30), 0),
+            Some(rect(30, 10, 10, 5))
+        );
+        assert_eq!(
+            trim(&image, rect(20, 0, 50, 50), 0),
+            Some(rect(30, 10, 10, 5))
+        );
+    }
+
+    #[test]
+    fn treats_colours_within_the_tolerance_as_background() {
+        let image = image(rect(10, 10, 3, 3), Rgba([108, 92, 100, 255]));
+        assert_eq!(trim(&image, rect(0, 0, 40, 30), 8), None);
+        assert_eq!(
+            trim(&image, rect(0, 0, 40, 30), 7),
+            Some(rect(10, 10, 3, 3))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+// Upper bound on remembered states, so long sessions don't grow without limit
+const MAX_HISTORY: usize = 256;
+
+/// Undo and redo stacks of snapshots of some interaction state.
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Records `state` as it was before an edit, discarding anything that could be redone.
+    pub fn record(&mut self, state: T) {
+        if self.undo.last() == Some(&state) {
+            return;
+        }
+        self.push_undo(state);
+        self.redo.clear();
+    }
+
+    /// Returns the state before the last edit, remembering `current` for redo.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    /// Returns the state before the last undo, remembering `current` for undo.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.push_undo(current);
+        Some(next)
+    }
+
+    fn push_undo(&mut self, state: T) {
+        if self.undo.len() == MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.undo.push(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_restore_states() {
+        let mut history = History::new();
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), None);
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), Some(3));
+        assert_eq!(history.redo(3), None);
+    }
+
+    #[test]
+    fn undo_stack_stays_within_the_cap() {
+        let mut history = History::new();
+        for state in 0..MAX_HISTORY {
+            history.record(state);
+        }
+        assert_eq!(history.undo(MAX_HISTORY), Some(MAX_HISTORY - 1));
+        assert_eq!(history.redo(MAX_HISTORY - 1), Some(MAX_HISTORY));
+        assert_eq!(history.undo.len(), MAX_HISTORY);
+        assert_eq!(history.undo.first(), Some(&0));
+        history.record(MAX_HISTORY);
+        assert_eq!(history.undo.len(), MAX_HISTORY);
+        assert_eq!(history.undo.first(), Some(&1));
+    }
+}